@@ -13,66 +13,280 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// Exact number of times the block must repeat (overrides the part preset)
+    #[arg(long)]
+    repeats: Option<usize>,
+
+    /// Minimum number of times the block must repeat (overrides the part preset)
+    #[arg(long)]
+    min_repeats: Option<usize>,
+
+    /// Longest block that is allowed to repeat
+    #[arg(long)]
+    max_block_len: Option<usize>,
+
+    /// Number base the ids are written in before looking for repeats (2-36)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..=36))]
+    radix: Option<u32>,
+
+    /// Merge overlapping ranges before summing so shared ids are only counted once
+    #[arg(long)]
+    merge: bool,
+
+    /// Write a report of reversed, overlapping and merged ranges along with the total
+    #[arg(long)]
+    report: bool,
+}
+
+impl Args {
+    // Start from one of the part presets and apply any rule options given on the command line
+    fn rule_from(&self, preset: RepetitionRule) -> RepetitionRule {
+        RepetitionRule {
+            exact_repeats: self.repeats.or(preset.exact_repeats),
+            min_repeats: self.min_repeats.unwrap_or(preset.min_repeats),
+            max_block_len: self.max_block_len.or(preset.max_block_len),
+            radix: self.radix.unwrap_or(preset.radix),
+        }
+    }
 }
-// Any ID which is made only of some sequence of digits repeated twice is invalid.
-// So, 55 (5 twice), 6464 (64 twice), and 123123 (123 twice) would all be invalid IDs.
-fn is_id_valid(id: i64) -> bool {
-    let id_str = id.to_string();
-    let len = id_str.len();
-    if len % 2 != 0 {
-        return true; // Odd length IDs are valid
-    }
-    let (first_half, second_half) = id_str.split_at(len / 2);
-    first_half != second_half
+
+// Describes which ids are invalid: an id is invalid when its digits, written in `radix`,
+// are some block of digits repeated a number of times allowed by the rule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RepetitionRule {
+    exact_repeats: Option<usize>,
+    min_repeats: usize,
+    max_block_len: Option<usize>,
+    radix: u32,
 }
 
-// Determines the sum of all invalid ids that appear in the given ranges. 
-fn solutioner_for_part_1(input: &str) -> String {
-    let mut sum_invalid_ids: i64 = 0;
-    let ranges_line = input.lines().next().unwrap();
-    for range in ranges_line.split(',') {
-        let parts: Vec<&str> = range.split('-').collect();
-        let start: i64 = parts[0].parse().unwrap();
-        let end: i64 = parts[1].parse().unwrap();
-        for id in start..=end {
-            if !is_id_valid(id) {
-                sum_invalid_ids += id;
+impl RepetitionRule {
+    // Part 1: an id is invalid if it is some sequence of digits repeated exactly twice.
+    // So, 55 (5 twice), 6464 (64 twice), and 123123 (123 twice) would all be invalid IDs.
+    fn exactly_twice() -> Self {
+        RepetitionRule {
+            exact_repeats: Some(2),
+            min_repeats: 2,
+            max_block_len: None,
+            radix: 10,
+        }
+    }
+
+    // Part 2: an id is invalid if it is some sequence of digits repeated at least twice
+    fn at_least_twice() -> Self {
+        RepetitionRule {
+            exact_repeats: None,
+            min_repeats: 2,
+            max_block_len: None,
+            radix: 10,
+        }
+    }
+
+    fn allows_repeats(&self, repeats: usize) -> bool {
+        repeats >= self.min_repeats && self.exact_repeats.is_none_or(|exact| repeats == exact)
+    }
+
+    fn allows_block_len(&self, block_len: usize) -> bool {
+        self.max_block_len.is_none_or(|max| block_len <= max)
+    }
+
+    fn is_id_valid(&self, id: i64) -> bool {
+        let id_str = to_radix_string(id, self.radix);
+        let len = id_str.len();
+        for block_len in 1..=(len / 2) {
+            if !len.is_multiple_of(block_len) {
+                continue;
+            }
+            let repeats = len / block_len;
+            if !self.allows_repeats(repeats) || !self.allows_block_len(block_len) {
+                continue;
+            }
+            let (block, _rest) = id_str.split_at(block_len);
+            if block.repeat(repeats) == id_str {
+                return false; // Found a repeating sequence the rule forbids
             }
         }
+        true // No forbidden repeating sequence found
     }
-    sum_invalid_ids.to_string()
 }
-// An Id is invalid if it is made only of some dequence of digits repeated at least twice 
-fn is_id_valid_updated(id: i64) -> bool {
-    let id_str = id.to_string();
-    let len = id_str.len();
-    for sub_len in 1..=(len / 2) {
-        if len % sub_len == 0 {
-            let (first_sub, _rest) = id_str.split_at(sub_len);
-            if first_sub.repeat(len / sub_len) == id_str {
-                return false; // Found a repeating sequence
+
+// Write a non-negative id out as digits in the given radix (2-36)
+fn to_radix_string(id: i64, radix: u32) -> String {
+    if radix == 10 {
+        return id.to_string();
+    }
+    let mut value = id.unsigned_abs();
+    if value == 0 {
+        return "0".to_string();
+    }
+    let mut digits = Vec::new();
+    while value > 0 {
+        let digit = (value % radix as u64) as u32;
+        digits.push(std::char::from_digit(digit, radix).unwrap());
+        value /= radix as u64;
+    }
+    digits.iter().rev().collect()
+}
+
+// An inclusive range of ids as written in the input, remembering where it appeared
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IdRange {
+    start: i64,
+    end: i64,
+    position: usize,
+}
+
+impl std::fmt::Display for IdRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+// Ranges may be spread across lines and separated by commas and/or whitespace.
+// Reversed ranges like 20-10 are kept but flipped so that start <= end, and are
+// returned separately so they can be reported.
+fn parse_ranges(input: &str) -> Result<(Vec<IdRange>, Vec<IdRange>), String> {
+    let mut ranges = Vec::new();
+    let mut reversed = Vec::new();
+    let tokens = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty());
+    for (position, token) in tokens.enumerate() {
+        let (start_str, end_str) = token
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid range '{}': expected start-end", token))?;
+        let start: i64 = start_str
+            .parse()
+            .map_err(|_| format!("Invalid range '{}': bad start '{}'", token, start_str))?;
+        let end: i64 = end_str
+            .parse()
+            .map_err(|_| format!("Invalid range '{}': bad end '{}'", token, end_str))?;
+        if start > end {
+            reversed.push(IdRange { start, end, position });
+            ranges.push(IdRange { start: end, end: start, position });
+        } else {
+            ranges.push(IdRange { start, end, position });
+        }
+    }
+    Ok((ranges, reversed))
+}
+
+// Sort the ranges and find every pair that shares at least one id
+fn find_overlaps(ranges: &[IdRange]) -> Vec<(IdRange, IdRange)> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_unstable_by_key(|r| (r.start, r.end));
+    let mut overlaps = Vec::new();
+    for (i, first) in sorted.iter().enumerate() {
+        for second in &sorted[i + 1..] {
+            if second.start > first.end {
+                break;
             }
+            overlaps.push((*first, *second));
         }
     }
-    true // No repeating sequence found
+    overlaps
+}
+
+// Merge overlapping ranges so no id is counted twice. Returns the merged ranges
+// along with each group of original ranges that was combined into one.
+fn merge_overlapping(ranges: &[IdRange]) -> (Vec<IdRange>, Vec<(Vec<IdRange>, IdRange)>) {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_unstable_by_key(|r| (r.start, r.end));
+
+    let mut groups: Vec<(Vec<IdRange>, IdRange)> = Vec::new();
+    for range in sorted {
+        if let Some((members, merged)) = groups.last_mut()
+            && range.start <= merged.end
+        {
+            merged.end = merged.end.max(range.end);
+            members.push(range);
+            continue;
+        }
+        groups.push((vec![range], range));
+    }
+
+    let merged_ranges = groups.iter().map(|(_, merged)| *merged).collect();
+    let merges = groups
+        .into_iter()
+        .filter(|(members, _)| members.len() > 1)
+        .collect();
+    (merged_ranges, merges)
 }
 
-// Determines the number of times the pointer "clicks" position 0
-fn solutioner_for_part_2(input: &str) -> String {
+// Determines the sum of all ids in the given ranges that are invalid under the rule.
+// With `merge` overlapping ranges are combined first so shared ids are only counted once,
+// and with `report` the total is preceded by what was found while reading the ranges.
+fn sum_invalid_ids(input: &str, rule: &RepetitionRule, merge: bool, report: bool) -> String {
+    let (ranges, reversed) = match parse_ranges(input) {
+        Ok(parsed) => parsed,
+        Err(e) => return e,
+    };
+    let overlaps = find_overlaps(&ranges);
+
+    let mut lines: Vec<String> = vec![format!("Read {} ranges", ranges.len())];
+    for range in &reversed {
+        lines.push(format!(
+            "Reversed range #{} {} read as {}-{}",
+            range.position + 1,
+            range,
+            range.end,
+            range.start
+        ));
+    }
+    for (first, second) in &overlaps {
+        lines.push(format!(
+            "Overlapping ranges #{} {} and #{} {}",
+            first.position + 1,
+            first,
+            second.position + 1,
+            second
+        ));
+    }
+
+    let ranges_to_sum = if merge {
+        let (merged_ranges, merges) = merge_overlapping(&ranges);
+        for (members, merged) in &merges {
+            let originals: Vec<String> = members.iter().map(|r| r.to_string()).collect();
+            lines.push(format!("Merged {} into {}", originals.join(", "), merged));
+        }
+        if merges.is_empty() {
+            lines.push("Nothing to merge".to_string());
+        }
+        merged_ranges
+    } else {
+        if !overlaps.is_empty() {
+            lines.push("Ranges not merged: ids in overlapping ranges are counted once per range".to_string());
+        }
+        ranges
+    };
+
     let mut sum_invalid_ids: i64 = 0;
-    let ranges_line = input.lines().next().unwrap();
-    for range in ranges_line.split(',') {
-        let parts: Vec<&str> = range.split('-').collect();
-        let start: i64 = parts[0].parse().unwrap();
-        let end: i64 = parts[1].parse().unwrap();
-        for id in start..=end {
-            if !is_id_valid_updated(id) {
+    for range in &ranges_to_sum {
+        for id in range.start..=range.end {
+            if !rule.is_id_valid(id) {
                 sum_invalid_ids += id;
             }
         }
     }
-    sum_invalid_ids.to_string()
 
+    if !report {
+        return sum_invalid_ids.to_string();
+    }
+    lines.push(format!("Sum of invalid ids: {}", sum_invalid_ids));
+    lines.join("\n")
+}
+
+// Determines the sum of all ids made of a sequence of digits repeated exactly twice
+fn solutioner_for_part_1(input: &str, args: &Args) -> String {
+    let rule = args.rule_from(RepetitionRule::exactly_twice());
+    sum_invalid_ids(input, &rule, args.merge, args.report)
+}
+
+// Determines the sum of all ids made of a sequence of digits repeated at least twice
+fn solutioner_for_part_2(input: &str, args: &Args) -> String {
+    let rule = args.rule_from(RepetitionRule::at_least_twice());
+    sum_invalid_ids(input, &rule, args.merge, args.report)
 }
 
 /// Main function to read input file, process it, and write to output file
@@ -87,8 +301,8 @@ fn main() -> io::Result<()> {
     input_file.read_to_string(&mut contents)?;
 
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+        1 => solutioner_for_part_1(&contents, &args),
+        2 => solutioner_for_part_2(&contents, &args),
         _ => "Invalid part specified".to_string(),
     };
 
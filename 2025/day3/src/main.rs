@@ -13,91 +13,205 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// Number of digits to pick from each line (defaults to 2 for part 1 and 12 for part 2)
+    #[arg(short, long)]
+    digits: Option<usize>,
+
+    /// Show the picked positions and runner-up for every line along with the total
+    #[arg(short, long)]
+    explain: bool,
 }
 
-// Find the largest two digit number in the string of digits
-// The two digits do not have to be adjacent but do have to be in the same order as the string
-fn find_largest_two_digit_number(input: &str) -> String {
-    let digits: Vec<char> = input.chars().collect();
-    let mut largest = "00".to_string();
-
-    for i in 0..digits.len() {
-        for j in (i + 1)..digits.len() {
-            let candidate = format!("{}{}", digits[i], digits[j]);
-            if candidate > largest {
-                largest = candidate;
-            }
+// Pick the positions of the lexicographically largest length-k subsequence of digits.
+// Walks the line once keeping a stack of chosen positions; a smaller digit on top of the
+// stack is dropped whenever a larger one arrives and there are still digits to spare,
+// so each position is pushed and popped at most once.
+fn select_largest_subsequence(digits: &[u8], k: usize) -> Option<Vec<usize>> {
+    if k > digits.len() {
+        return None;
+    }
+    let mut drops_left = digits.len() - k;
+    let mut stack: Vec<usize> = Vec::with_capacity(digits.len());
+    for (i, &digit) in digits.iter().enumerate() {
+        while drops_left > 0 && stack.last().is_some_and(|&top| digits[top] < digit) {
+            stack.pop();
+            drops_left -= 1;
         }
+        stack.push(i);
     }
-    largest
+    stack.truncate(k);
+    Some(stack)
+}
+
+// Find the largest k digit number in the string of digits
+// The digits do not have to be adjacent but do have to be in the same order as the string
+fn find_largest_number(input: &str, k: usize) -> Option<String> {
+    let digits = input.as_bytes();
+    let positions = select_largest_subsequence(digits, k)?;
+    Some(positions.iter().map(|&i| digits[i] as char).collect())
+}
 
+// Add two non-negative decimal numbers written as strings
+fn add_decimal_strings(a: &str, b: &str) -> String {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut a_digits = a.bytes().rev();
+    let mut b_digits = b.bytes().rev();
+    let mut carry = 0;
+    loop {
+        let (x, y) = (a_digits.next(), b_digits.next());
+        if x.is_none() && y.is_none() && carry == 0 {
+            break;
+        }
+        let sum = x.map_or(0, |d| d - b'0') + y.map_or(0, |d| d - b'0') + carry;
+        result.push(b'0' + sum % 10);
+        carry = sum / 10;
+    }
+    let total: String = result.iter().rev().map(|&d| d as char).collect();
+    let trimmed = total.trim_start_matches('0');
+    if trimmed.is_empty() { "0".to_string() } else { trimmed.to_string() }
 }
-// Split the input into lines of digits and then add the largest two digit 
-// number to a running total, returning the total as a string
-fn solutioner_for_part_1(input: &str) -> String {
-    let lines = input.lines();
-    let mut total = 0;
-    // print line
-    
-    for line in lines {
-        println!("Processing line: {}", line);
-        let largest = find_largest_two_digit_number(line);
-        println!("Largest two-digit number in line: {}", largest);
-        total += largest.parse::<i32>().unwrap();
+
+// Sum numbers as u128 while they fit (up to 38 digits each), falling back to adding the
+// decimal strings directly when a number or the running total is too big
+fn sum_numbers(numbers: &[String]) -> String {
+    let total = numbers.iter().try_fold(0u128, |total, number| {
+        number.parse::<u128>().ok().and_then(|n| total.checked_add(n))
+    });
+    match total {
+        Some(total) => total.to_string(),
+        None => numbers
+            .iter()
+            .fold("0".to_string(), |total, number| add_decimal_strings(&total, number)),
     }
-    total.to_string()
 }
-// Find the largest 12 digit subsequence while keeping original order
-fn find_largest_twelve_digit_number(input: &str) -> String {
-    let digits: Vec<char> = input.chars().collect();
-    let n = digits.len();
-    let target_len = 12;
 
-    // dp[i][k] = best length-k subsequence using digits starting at i (inclusive)
-    // using dynamic programming to build the solution
-    let mut dp: Vec<Vec<Option<String>>> = vec![vec![None; target_len + 1]; n + 1];
-    for i in 0..=n {
-        dp[i][0] = Some(String::new());
+// Subtract two non-negative decimal numbers written as strings where a >= b
+fn subtract_decimal_strings(a: &str, b: &str) -> String {
+    let mut result = Vec::with_capacity(a.len());
+    let mut b_digits = b.bytes().rev();
+    let mut borrow = 0;
+    for x in a.bytes().rev() {
+        let x = (x - b'0') as i8;
+        let y = b_digits.next().map_or(0, |d| (d - b'0') as i8);
+        let mut diff = x - y - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 10;
+            borrow = 1;
+        }
+        result.push(b'0' + diff as u8);
     }
+    let difference: String = result.iter().rev().map(|&d| d as char).collect();
+    let trimmed = difference.trim_start_matches('0');
+    if trimmed.is_empty() { "0".to_string() } else { trimmed.to_string() }
+}
+
+// Find the positions of the second largest distinct length-k value. The runner-up matches
+// the best pick for as long a prefix as possible, then takes the largest smaller digit that
+// still leaves room for the remaining digits, and finishes with the best pick of the rest.
+fn select_runner_up(digits: &[u8], best: &[usize]) -> Option<Vec<usize>> {
+    let k = best.len();
+    let n = digits.len();
 
-    for i in (0..n).rev() {
-        for k in 1..=target_len {
-            // Option 1: skip current digit
-            let exclude = dp[i + 1][k].clone();
-            // Option 2: take current digit if we can still fill remaining length
-            let include = if k <= n - i {
-                dp[i + 1][k - 1]
-                    .as_ref()
-                    .map(|suffix| format!("{}{}", digits[i], suffix))
-            } else {
-                None
-            };
-
-            dp[i][k] = [include, exclude]
-                .into_iter()
-                .flatten()
-                .max();
+    // Match the best digits at their earliest possible positions; prefix_end[p] is where
+    // the match of the first p digits ends (exclusive) and leaves the most room after it
+    let mut earliest = Vec::with_capacity(k);
+    let mut prefix_end = vec![0; k + 1];
+    let mut next = 0;
+    for (p, &b) in best.iter().enumerate() {
+        while digits[next] != digits[b] {
+            next += 1;
+        }
+        earliest.push(next);
+        next += 1;
+        prefix_end[p + 1] = next;
+    }
 
+    for p in (0..k).rev() {
+        let remaining = k - p - 1;
+        let window = prefix_end[p]..(n - remaining);
+        let mut choice: Option<usize> = None;
+        for j in window {
+            if digits[j] < digits[best[p]] && choice.is_none_or(|c| digits[j] > digits[c]) {
+                choice = Some(j);
+            }
         }
+        let Some(j) = choice else {
+            continue;
+        };
+        let rest = select_largest_subsequence(&digits[j + 1..], remaining)?;
+        let mut positions: Vec<usize> = earliest[..p].to_vec();
+        positions.push(j);
+        positions.extend(rest.iter().map(|&r| r + j + 1));
+        return Some(positions);
     }
+    None
+}
+
+// Show which digits of the line were picked, with a ^ under each chosen position
+fn explain_line(line_number: usize, line: &str, k: usize) -> Option<String> {
+    let digits = line.as_bytes();
+    let best = select_largest_subsequence(digits, k)?;
+    let value: String = best.iter().map(|&i| digits[i] as char).collect();
 
-    dp[0][target_len]
-        .clone()
-        .expect("input always contains at least 12 digits")
+    let mut markers = vec![b' '; digits.len()];
+    for &i in &best {
+        markers[i] = b'^';
+    }
+    let label = format!("Line {}: ", line_number);
+    let mut lines = vec![
+        format!("{}{}", label, line),
+        format!("{}{}", " ".repeat(label.len()), String::from_utf8_lossy(&markers).trim_end()),
+        format!("Positions: {:?}", best),
+        format!("Largest: {}", value),
+    ];
+    match select_runner_up(digits, &best) {
+        Some(runner_up) => {
+            let runner_up_value: String = runner_up.iter().map(|&i| digits[i] as char).collect();
+            lines.push(format!(
+                "Runner-up: {} at {:?} (short by {})",
+                runner_up_value,
+                runner_up,
+                subtract_decimal_strings(&value, &runner_up_value)
+            ));
+        }
+        None => lines.push("Runner-up: none, every pick gives the same value".to_string()),
+    }
+    Some(lines.join("\n"))
 }
-// Find the sum of the largest twelve digit numbers from each line of input 
-fn solutioner_for_part_2(input: &str) -> String {
-    let lines = input.lines();
-    let mut total = 0;
-    // print line
-    
-    for line in lines {
-        println!("Processing line: {}", line);
-        let largest = find_largest_twelve_digit_number(line);
-        println!("Largest twelve-digit number in line: {}", largest);
-        total += largest.parse::<i64>().unwrap();
+
+// Split the input into lines of digits and then add the largest k digit
+// number from each line to a running total, returning the total as a string.
+// With `explain` each line's picked positions and runner-up come before the total.
+fn sum_largest_numbers(input: &str, k: usize, explain: bool) -> String {
+    let mut largest_numbers = Vec::new();
+    let mut explanations = Vec::new();
+    for (line_idx, line) in input.lines().enumerate() {
+        let Some(largest) = find_largest_number(line, k) else {
+            return format!("Line has fewer than {} digits: {}", k, line);
+        };
+        if explain && let Some(explanation) = explain_line(line_idx + 1, line, k) {
+            explanations.push(explanation);
+        }
+        largest_numbers.push(largest);
+    }
+    let total = sum_numbers(&largest_numbers);
+    if !explain {
+        return total;
     }
-    total.to_string()
+    explanations.push(format!("Total: {}", total));
+    explanations.join("\n\n")
+}
+
+// Sum of the largest two digit numbers from each line of input, unless --digits says otherwise
+fn solutioner_for_part_1(input: &str, args: &Args) -> String {
+    sum_largest_numbers(input, args.digits.unwrap_or(2), args.explain)
+}
+
+// Sum of the largest twelve digit numbers from each line of input, unless --digits says otherwise
+fn solutioner_for_part_2(input: &str, args: &Args) -> String {
+    sum_largest_numbers(input, args.digits.unwrap_or(12), args.explain)
 }
 
 /// Main function to read input file, process it, and write to output file
@@ -112,8 +226,8 @@ fn main() -> io::Result<()> {
     input_file.read_to_string(&mut contents)?;
 
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+        1 => solutioner_for_part_1(&contents, &args),
+        2 => solutioner_for_part_2(&contents, &args),
         _ => "Invalid part specified".to_string(),
     };
 
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs::File;
 use std::io::{self, Read, Write};
 
@@ -13,7 +13,126 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// Which cells count as neighbours
+    #[arg(short, long, value_enum, default_value_t = Neighbourhood::Moore)]
+    neighbourhood: Neighbourhood,
+
+    /// How far the neighbourhood reaches from the cell
+    #[arg(short, long, default_value_t = 1)]
+    radius: usize,
+
+    /// A roll is accessible when it has fewer than this many occupied neighbours
+    #[arg(short, long, default_value_t = 4)]
+    threshold: usize,
+
+    /// What happens to neighbours that fall outside the grid
+    #[arg(short, long, value_enum, default_value_t = Edges::Bounded)]
+    edges: Edges,
+
+    /// Play the removal waves as an animation in the terminal (part 2)
+    #[arg(short, long)]
+    animate: bool,
+
+    /// Milliseconds to pause between animation frames
+    #[arg(long, default_value_t = 150)]
+    frame_delay: u64,
+
+    /// Directory to write one plain-text frame per wave into (part 2)
+    #[arg(short, long)]
+    frames: Option<String>,
+
+    /// Print the grid with each removed roll labelled by its wave in base-36 (part 2)
+    #[arg(short, long)]
+    labels: bool,
+
+    /// Read the input as 3D voxel layers separated by blank lines
+    #[arg(long)]
+    voxels: bool,
+
+    /// Which voxels count as neighbours when reading --voxels
+    #[arg(short, long, value_enum, default_value_t = Connectivity::All)]
+    connectivity: Connectivity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Neighbourhood {
+    /// Cells within `radius` steps up, down, left or right (Manhattan distance)
+    VonNeumann,
+    /// Cells within `radius` steps in any direction including diagonals (Chebyshev distance)
+    Moore,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Edges {
+    /// Neighbours outside the grid are ignored
+    Bounded,
+    /// The grid wraps around so the left edge touches the right and the top touches the bottom
+    /// (and the first voxel layer touches the last)
+    Wrap,
+    /// Neighbours outside the grid always count as occupied
+    Occupied,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Connectivity {
+    /// Voxels sharing a face
+    #[value(name = "6")]
+    Faces,
+    /// Voxels sharing a face or an edge
+    #[value(name = "18")]
+    FacesAndEdges,
+    /// Voxels sharing a face, an edge or a corner
+    #[value(name = "26")]
+    All,
+}
+
+// A (layer, row, column) step from a cell to one of its neighbours
+type Offset = (isize, isize, isize);
+
+impl Neighbourhood {
+    // Offsets from a cell to each of its neighbours within its own layer
+    fn offsets(&self, radius: usize) -> Vec<Offset> {
+        let r = radius as isize;
+        let mut offsets = Vec::new();
+        for dx in -r..=r {
+            for dy in -r..=r {
+                let in_reach = match self {
+                    Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= r,
+                    Neighbourhood::Moore => true,
+                };
+                if in_reach && (dx, dy) != (0, 0) {
+                    offsets.push((0, dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl Connectivity {
+    // Offsets from a voxel to each of its neighbours in the 3x3x3 cube around it
+    fn offsets(&self) -> Vec<Offset> {
+        let max_steps = match self {
+            Connectivity::Faces => 1,
+            Connectivity::FacesAndEdges => 2,
+            Connectivity::All => 3,
+        };
+        let mut offsets = Vec::new();
+        for dz in -1isize..=1 {
+            for dx in -1isize..=1 {
+                for dy in -1isize..=1 {
+                    let steps = [dz, dx, dy].iter().filter(|&&d| d != 0).count();
+                    if steps > 0 && steps <= max_steps {
+                        offsets.push((dz, dx, dy));
+                    }
+                }
+            }
+        }
+        offsets
+    }
+}
+
 // Converts a string of lines of . and @ characters into a 2D vector of strings
 fn convert_string_into_two_d_vector(input: &str) -> Vec<Vec<String>> {
     let mut grid: Vec<Vec<String>> = Vec::new();
@@ -24,100 +143,266 @@ fn convert_string_into_two_d_vector(input: &str) -> Vec<Vec<String>> {
     grid
 }
 
-// check if the eight directions arround a position have 4 or more @ characters
-fn has_four_or_more_adjacent_tp(
-    grid: &Vec<Vec<String>>,
-    x: usize,
-    y: usize,
-    directions: &Vec<(isize, isize)>,
-) -> bool {
-    let mut adjacent_count = 0;
-    for (dx, dy) in directions {
-        let ni = x as isize + dx;
-        let nj = y as isize + dy;
-        if ni >= 0
-            && ni < grid.len() as isize
-            && nj >= 0
-            && nj < grid[x].len() as isize
-            && grid[ni as usize][nj as usize] == "@"
-        {
-            adjacent_count += 1;
-        }
+// Split the input into 2D layers. A flat puzzle is a single layer and voxel input has
+// one layer per block of lines, with blank lines between the blocks.
+fn convert_string_into_layers(input: &str, voxels: bool) -> Vec<Vec<Vec<String>>> {
+    if !voxels {
+        return vec![convert_string_into_two_d_vector(input)];
     }
-    adjacent_count >= 4
-}
-
-// Build a grid and count the number of @ characters that have less than 4 adjacent @ characters 
-fn solutioner_for_part_1(input: &str) -> String {
-    let grid = convert_string_into_two_d_vector(input);
-    let directions = vec![
-        (-1, -1), (-1, 0), (-1, 1),
-        (0, -1), (0, 1), (1, -1),
-        (1, 0), (1, 1),
-    ];
-    let mut count = 0;
-    for i in 0..grid.len() {
-        for j in 0..grid[i].len() {
-            if grid[i][j] == "@" {
-                if !has_four_or_more_adjacent_tp(&grid, i, j, &directions) {
-                    count += 1;
-                }
+    // Any run of blank lines ends a layer, however many there are
+    let mut layers: Vec<Vec<Vec<String>>> = Vec::new();
+    let mut layer: Vec<Vec<String>> = Vec::new();
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            if !layer.is_empty() {
+                layers.push(std::mem::take(&mut layer));
             }
+            continue;
         }
+        layer.push(line.chars().map(|c| c.to_string()).collect());
+    }
+    if !layer.is_empty() {
+        layers.push(layer);
+    }
+    layers
+}
+
+// Outcome of peeling: how many cells each wave removed and the wave (starting at 1)
+// that removed each cell, if any
+struct Peeling {
+    wave_sizes: Vec<usize>,
+    removal_wave: Vec<Option<usize>>,
+}
+
+impl Peeling {
+    fn removed(&self) -> usize {
+        self.wave_sizes.iter().sum()
+    }
+
+    fn waves(&self) -> usize {
+        self.wave_sizes.len()
     }
-    count.to_string()
 }
 
+// Repeatedly remove occupied cells that have fewer than `threshold` occupied neighbours
+// until none are left to remove. Cells are identified by index and `neighbours_of` fills
+// in the neighbours of a cell, returning how many more neighbours lie outside the cells
+// and always count as occupied. Each cell keeps a count of its occupied neighbours; removing
+// a cell decrements its neighbours' counts and any that drop below the threshold join the
+// next wave, so every cell is queued at most once and the peeling is linear in the edges.
+// A wave is every cell that would have been removed together in one full rescan.
+fn peel<F>(occupied: &[bool], neighbours_of: F, threshold: usize) -> Peeling
+where
+    F: Fn(usize, &mut Vec<usize>) -> usize,
+{
+    let mut neighbours = Vec::new();
+    let mut counts = vec![0usize; occupied.len()];
+    let mut present = occupied.to_vec();
+    let mut removal_wave = vec![None; occupied.len()];
 
-// Keep removing @ characters that have less than 4 adjacent @ characters 
-// until no more can be removed
-fn solutioner_for_part_2(input: &str) -> String {
-    let mut grid = convert_string_into_two_d_vector(input);
-    let directions = vec![
-        (-1, -1), (-1, 0), (-1, 1),
-        (0, -1), (0, 1),
-        (1, -1), (1, 0), (1, 1),
-    ];
+    let mut current_wave = Vec::new();
+    for cell in (0..occupied.len()).filter(|&cell| occupied[cell]) {
+        neighbours.clear();
+        let always_occupied = neighbours_of(cell, &mut neighbours);
+        counts[cell] = always_occupied + neighbours.iter().filter(|&&n| occupied[n]).count();
+        if counts[cell] < threshold {
+            current_wave.push(cell);
+        }
+    }
 
-    let mut tp_positions: Vec<(usize, usize)> = vec![];
+    let mut wave_sizes = Vec::new();
+    while !current_wave.is_empty() {
+        wave_sizes.push(current_wave.len());
+        for &cell in &current_wave {
+            present[cell] = false;
+            removal_wave[cell] = Some(wave_sizes.len());
+        }
 
-    // Collect all @ positions
-    for i in 0..grid.len() {
-        for j in 0..grid[i].len() {
-            if grid[i][j] == "@" {
-                tp_positions.push((i, j));
+        let mut next_wave = Vec::new();
+        for &cell in &current_wave {
+            neighbours.clear();
+            neighbours_of(cell, &mut neighbours);
+            for &n in &neighbours {
+                if !present[n] {
+                    continue;
+                }
+                counts[n] -= 1;
+                // Only queue a cell the moment it crosses the threshold
+                if counts[n] + 1 == threshold {
+                    next_wave.push(n);
+                }
             }
         }
+        current_wave = next_wave;
     }
 
-    let mut count = 0;
-    let mut changed = true;
+    Peeling { wave_sizes, removal_wave }
+}
 
-    // Keep removing until no more valid tp available 
-    while changed {
-        changed = false;
-        let mut to_remove = vec![];
+// Rows and layers may be ragged, so cells are indexed as if every layer had as many rows
+// as the tallest and every row were as long as the longest
+fn layer_dimensions(layers: &[Vec<Vec<String>>]) -> (usize, usize) {
+    let height = layers.iter().map(|grid| grid.len()).max().unwrap_or(0);
+    let width = layers
+        .iter()
+        .flat_map(|grid| grid.iter().map(|row| row.len()))
+        .max()
+        .unwrap_or(0);
+    (height, width)
+}
 
-        // Find all positions with < 4 adjacent tp 
-        for &(x, y) in &tp_positions {
-            if !has_four_or_more_adjacent_tp(&grid, x, y, &directions) {
-                to_remove.push((x, y));
+// Flatten the layers into occupied cells and peel them using the neighbourhood, threshold
+// and edge behaviour from the command line
+fn peel_layers(layers: &[Vec<Vec<String>>], args: &Args) -> Peeling {
+    let depth = layers.len();
+    let (height, width) = layer_dimensions(layers);
+    let offsets = if args.voxels {
+        args.connectivity.offsets()
+    } else {
+        args.neighbourhood.offsets(args.radius)
+    };
+
+    let occupied: Vec<bool> = (0..depth * height * width)
+        .map(|cell| {
+            let (k, i, j) = (cell / (height * width), cell / width % height, cell % width);
+            layers[k].get(i).and_then(|row| row.get(j)).is_some_and(|c| c == "@")
+        })
+        .collect();
+
+    peel(
+        &occupied,
+        |cell, neighbours| {
+            let z = (cell / (height * width)) as isize;
+            let x = (cell / width % height) as isize;
+            let y = (cell % width) as isize;
+            let (d, h, w) = (depth as isize, height as isize, width as isize);
+            let mut outside = 0;
+            for (dz, dx, dy) in &offsets {
+                let (mut nk, mut ni, mut nj) = (z + dz, x + dx, y + dy);
+                if args.edges == Edges::Wrap {
+                    nk = nk.rem_euclid(d);
+                    ni = ni.rem_euclid(h);
+                    nj = nj.rem_euclid(w);
+                }
+                if nk >= 0 && nk < d && ni >= 0 && ni < h && nj >= 0 && nj < w {
+                    let neighbour = (nk as usize * height + ni as usize) * width + nj as usize;
+                    // With a radius as big as the grid, wrapping can land back on the cell itself
+                    if neighbour != cell {
+                        neighbours.push(neighbour);
+                    }
+                } else if args.edges == Edges::Occupied {
+                    outside += 1;
+                }
             }
-        }
+            outside
+        },
+        args.threshold,
+    )
+}
 
-        // Remove them
-        if !to_remove.is_empty() {
-            changed = true;
-            count += to_remove.len();
+// Count the number of @ characters that have less than 4 adjacent @ characters,
+// which is everything removed in the first wave of peeling
+fn solutioner_for_part_1(input: &str, args: &Args) -> String {
+    let layers = convert_string_into_layers(input, args.voxels);
+    let peeling = peel_layers(&layers, args);
+    peeling.wave_sizes.first().copied().unwrap_or(0).to_string()
+}
 
-            for (x, y) in to_remove {
-                grid[x][y] = "#".to_string();
-                tp_positions.retain(|&pos| pos != (x, y));
+// Draw every layer cell by cell, letting `draw` decide what to show for cells that were
+// removed, with a blank line between layers
+fn render_layers<F>(layers: &[Vec<Vec<String>>], peeling: &Peeling, draw: F) -> String
+where
+    F: Fn(&mut String, &str, Option<usize>),
+{
+    let (height, width) = layer_dimensions(layers);
+    let mut rendered = Vec::new();
+    for (k, grid) in layers.iter().enumerate() {
+        let mut layer = String::new();
+        for (i, row) in grid.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                draw(&mut layer, cell, peeling.removal_wave[(k * height + i) * width + j]);
             }
+            layer.push('\n');
         }
+        rendered.push(layer);
     }
+    rendered.join("\n")
+}
 
-    count.to_string()
+// Draw the grid as it looks after `wave`: rolls still standing are @, rolls removed in
+// this wave are x (red when `ansi` is set) and rolls removed earlier are back to .
+fn render_frame(layers: &[Vec<Vec<String>>], peeling: &Peeling, wave: usize, ansi: bool) -> String {
+    render_layers(layers, peeling, |frame, cell, removed_in| match removed_in {
+        Some(w) if w == wave && ansi => frame.push_str("\x1b[1;31mx\x1b[0m"),
+        Some(w) if w == wave => frame.push('x'),
+        Some(w) if w < wave => frame.push('.'),
+        _ => frame.push_str(cell),
+    })
+}
+
+// Draw the final grid with every removed roll replaced by its wave number in base-36.
+// Past 35 waves one character is not enough, so every cell gets as many characters as
+// the last wave needs: labels are zero-padded and other cells repeat their character.
+fn render_wave_labels(layers: &[Vec<Vec<String>>], peeling: &Peeling) -> String {
+    let mut cell_width = 1;
+    while 36usize.pow(cell_width as u32) <= peeling.waves() {
+        cell_width += 1;
+    }
+    render_layers(layers, peeling, |labelled, cell, removed_in| match removed_in {
+        Some(w) => labelled.push_str(&format!("{:0>width$}", to_base_36(w), width = cell_width)),
+        None => labelled.push_str(&cell.repeat(cell_width)),
+    })
+}
+
+fn to_base_36(mut value: usize) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((value % 36) as u32, 36).unwrap());
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+// Clear the terminal and redraw the grid once per wave
+fn play_animation(layers: &[Vec<Vec<String>>], peeling: &Peeling, frame_delay: u64) {
+    for wave in 0..=peeling.waves() {
+        print!("\x1b[2J\x1b[H{}", render_frame(layers, peeling, wave, true));
+        println!("Wave {}/{}", wave, peeling.waves());
+        std::thread::sleep(std::time::Duration::from_millis(frame_delay));
+    }
+}
+
+// Write frame_000.txt (the starting grid) through one file per wave into `dir`
+fn write_frames(layers: &[Vec<Vec<String>>], peeling: &Peeling, dir: &str) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for wave in 0..=peeling.waves() {
+        let path = std::path::Path::new(dir).join(format!("frame_{:03}.txt", wave));
+        std::fs::write(path, render_frame(layers, peeling, wave, false))?;
+    }
+    Ok(())
+}
+
+// Keep removing @ characters that have less than 4 adjacent @ characters
+// until no more can be removed, reporting how many waves of removals it took
+fn solutioner_for_part_2(input: &str, args: &Args) -> io::Result<String> {
+    let layers = convert_string_into_layers(input, args.voxels);
+    let peeling = peel_layers(&layers, args);
+
+    if args.animate {
+        play_animation(&layers, &peeling, args.frame_delay);
+    }
+    if let Some(dir) = &args.frames {
+        write_frames(&layers, &peeling, dir)?;
+    }
+
+    println!("Waves: {}", peeling.waves());
+    if args.labels {
+        print!("{}", render_wave_labels(&layers, &peeling));
+    }
+    Ok(peeling.removed().to_string())
 }
 
 /// Main function to read input file, process it, and write to output file
@@ -132,8 +417,8 @@ fn main() -> io::Result<()> {
     input_file.read_to_string(&mut contents)?;
 
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+        1 => solutioner_for_part_1(&contents, &args),
+        2 => solutioner_for_part_2(&contents, &args)?,
         _ => "Invalid part specified".to_string(),
     };
 
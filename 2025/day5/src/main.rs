@@ -1,6 +1,7 @@
 use clap::Parser;
+use std::collections::BTreeSet;
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 
 #[derive(Parser)]
 #[command(name = "solutions_runner")]
@@ -13,73 +14,160 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i64,
-}
 
-fn split_input_into_two_arrays(input: &str) -> (Vec<(i64, i64)>, Vec<i64>) {
-    let mut sections = input.split("\n\n");
-    let first_section = sections.next().unwrap_or("");
-    let second_section = sections.next().unwrap_or("");
+    /// Stream ids to check from this file (or - for stdin) instead of the input's id section
+    #[arg(long)]
+    ids: Option<String>,
 
-    let fresh_id_ranges = first_section
-        .lines()
-        .filter_map(|range| {
-            let (start_str, end_str) = range.split_once('-')?;
-            let start: i64 = start_str.parse().ok()?;
-            let end: i64 = end_str.parse().ok()?;
-            (start <= end).then_some((start, end))
-        })
-        .collect();
+    /// Report dropped lines, which ranges contain each id and how the ranges were merged
+    #[arg(short, long)]
+    diagnose: bool,
 
-    let ids_to_check = second_section
-        .lines()
-        .filter_map(|id| id.parse::<i64>().ok())
-        .collect();
+    /// Read each range line as a 2D or 3D box (x1-x2,y1-y2[,z1-z2]) and the ids as points
+    #[arg(short, long)]
+    boxes: bool,
+}
 
-    (fresh_id_ranges, ids_to_check)
+// A line of input that could not be used, with the reason it was skipped
+struct DroppedLine {
+    line_number: usize,
+    text: String,
+    reason: String,
 }
 
+// Everything read from the input, remembering which line each range came from
+// and which lines had to be dropped
+struct ParsedInput {
+    ranges: Vec<(i64, i64)>,
+    range_lines: Vec<usize>,
+    ids: Vec<i64>,
+    dropped: Vec<DroppedLine>,
+}
 
-// Determine which ids are valid based on the ranges provided. 
-fn solutioner_for_part_1(input: &str) -> String {
-    let (fresh_id_ranges, ids_to_check) = split_input_into_two_arrays(input);
-    let mut count_of_valid_ids = 0;
-    for id in ids_to_check {
-        let mut is_valid = false;
-        for range in &fresh_id_ranges {
-            if id >= range.0 && id <= range.1 {
-                is_valid = true;
-                break;
-            } 
+fn parse_range(range: &str) -> Result<(i64, i64), &'static str> {
+    let (start_str, end_str) = range.split_once('-').ok_or("no - between start and end")?;
+    let start: i64 = start_str.parse().map_err(|_| "start is not a number")?;
+    let end: i64 = end_str.parse().map_err(|_| "end is not a number")?;
+    if start > end {
+        return Err("start is after end");
+    }
+    Ok((start, end))
+}
+
+// The ranges come first, then a blank line, then the ids to check. Blank lines are
+// skipped quietly and any other line that can't be read is recorded as dropped,
+// including everything after a second blank line separator.
+fn parse_input(input: &str) -> ParsedInput {
+    let mut parsed = ParsedInput {
+        ranges: Vec::new(),
+        range_lines: Vec::new(),
+        ids: Vec::new(),
+        dropped: Vec::new(),
+    };
+    let drop_line = |line_number: usize, text: &str, reason: &str| DroppedLine {
+        line_number,
+        text: text.to_string(),
+        reason: reason.to_string(),
+    };
+
+    // 0 while reading ranges, 1 while reading ids and 2 or more past the ids
+    let mut section = 0;
+    let mut previous_blank = false;
+    for (idx, line) in input.lines().enumerate() {
+        let line_number = idx + 1;
+        if line.trim().is_empty() {
+            // A run of blank lines only ends one section
+            if !previous_blank && line_number > 1 {
+                section += 1;
+            }
+            previous_blank = true;
+            continue;
         }
-        if is_valid {
-            count_of_valid_ids += 1;
+        previous_blank = false;
+
+        match section {
+            0 => match parse_range(line) {
+                Ok(parsed_range) => {
+                    parsed.ranges.push(parsed_range);
+                    parsed.range_lines.push(line_number);
+                }
+                Err(reason) => parsed.dropped.push(drop_line(line_number, line, reason)),
+            },
+            1 => match line.parse::<i64>() {
+                Ok(id) => parsed.ids.push(id),
+                Err(_) => parsed
+                    .dropped
+                    .push(drop_line(line_number, line, "id is not a number")),
+            },
+            _ => parsed
+                .dropped
+                .push(drop_line(line_number, line, "comes after the ids section")),
         }
     }
-    count_of_valid_ids.to_string()
+
+    parsed
 }
 
-// Determine how many ingredient ids are considered to be fresh given the ranges provided
-fn solutioner_for_part_2(input: &str) -> String {
-    let (fresh_id_ranges, _ids_to_check) = split_input_into_two_arrays(input);
+fn split_input_into_two_arrays(input: &str) -> (Vec<(i64, i64)>, Vec<i64>) {
+    let parsed = parse_input(input);
+    (parsed.ranges, parsed.ids)
+}
 
-    let mut ranges = fresh_id_ranges;
-    ranges.sort_unstable_by_key(|r| r.0);
+// Sort the ranges and merge any that overlap or touch so the result is disjoint and ordered.
+// Alongside each merged range are the indexes of the original ranges that went into it.
+fn merge_ranges_tracking_sources(fresh_id_ranges: &[(i64, i64)]) -> Vec<((i64, i64), Vec<usize>)> {
+    let mut order: Vec<usize> = (0..fresh_id_ranges.len()).collect();
+    order.sort_unstable_by_key(|&idx| fresh_id_ranges[idx].0);
 
     // Merge overlapping ranges
-    let mut merged = Vec::<(i64, i64)>::new();
-    for range in ranges {
-        if let Some(last) = merged.last_mut() {
+    let mut merged = Vec::<((i64, i64), Vec<usize>)>::new();
+    for idx in order {
+        let range = fresh_id_ranges[idx];
+        if let Some((last, sources)) = merged.last_mut() {
             if range.0 <= last.1 + 1 {
                 // Overlapping or adjacent - merge
                 last.1 = last.1.max(range.1);
+                sources.push(idx);
             } else {
                 // Not overlapping - add new range
-                merged.push(range);
+                merged.push((range, vec![idx]));
             }
         } else {
-            merged.push(range);
+            merged.push((range, vec![idx]));
         }
     }
+    merged
+}
+
+// Sort the ranges and merge any that overlap or touch so the result is disjoint and ordered
+fn merge_ranges(fresh_id_ranges: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    merge_ranges_tracking_sources(&fresh_id_ranges)
+        .into_iter()
+        .map(|(range, _sources)| range)
+        .collect()
+}
+
+// Binary search the merged ranges for the last one starting at or before the id
+fn is_fresh(merged: &[(i64, i64)], id: i64) -> bool {
+    let candidates = merged.partition_point(|r| r.0 <= id);
+    candidates > 0 && id <= merged[candidates - 1].1
+}
+
+// Determine which ids are valid based on the ranges provided. 
+fn solutioner_for_part_1(input: &str) -> String {
+    let (fresh_id_ranges, ids_to_check) = split_input_into_two_arrays(input);
+    let merged = merge_ranges(fresh_id_ranges);
+    let count_of_valid_ids = ids_to_check
+        .into_iter()
+        .filter(|&id| is_fresh(&merged, id))
+        .count();
+    count_of_valid_ids.to_string()
+}
+
+// Determine how many ingredient ids are considered to be fresh given the ranges provided
+fn solutioner_for_part_2(input: &str) -> String {
+    let (fresh_id_ranges, _ids_to_check) = split_input_into_two_arrays(input);
+    let merged = merge_ranges(fresh_id_ranges);
 
     let count: i64 = merged.iter()
         .map(|(start, end)| end - start + 1)
@@ -88,6 +176,273 @@ fn solutioner_for_part_2(input: &str) -> String {
     count.to_string()
 }
 
+// Explain how the input was read: which lines were dropped and why, which of the original
+// ranges make each id fresh, and how the ranges collapsed when they were merged
+fn solutioner_for_diagnostics(input: &str) -> String {
+    let parsed = parse_input(input);
+    let describe = |idx: usize| {
+        let (start, end) = parsed.ranges[idx];
+        format!("{}-{} (line {})", start, end, parsed.range_lines[idx])
+    };
+    let mut report = Vec::new();
+
+    report.push(format!("Dropped lines: {}", parsed.dropped.len()));
+    for dropped in &parsed.dropped {
+        report.push(format!(
+            "  line {} \"{}\": {}",
+            dropped.line_number, dropped.text, dropped.reason
+        ));
+    }
+
+    report.push(format!("Ids: {}", parsed.ids.len()));
+    let mut fresh_ids = 0;
+    for &id in &parsed.ids {
+        let containing: Vec<String> = (0..parsed.ranges.len())
+            .filter(|&idx| parsed.ranges[idx].0 <= id && id <= parsed.ranges[idx].1)
+            .map(describe)
+            .collect();
+        if containing.is_empty() {
+            report.push(format!("  {}: spoiled", id));
+        } else {
+            fresh_ids += 1;
+            report.push(format!("  {}: fresh, in {}", id, containing.join(", ")));
+        }
+    }
+
+    let merged = merge_ranges_tracking_sources(&parsed.ranges);
+    report.push(format!(
+        "Merging: {} ranges into {}",
+        parsed.ranges.len(),
+        merged.len()
+    ));
+    for ((start, end), sources) in &merged {
+        let originals: Vec<String> = sources.iter().map(|&idx| describe(idx)).collect();
+        if sources.len() == 1 {
+            report.push(format!("  {} stays as is", originals[0]));
+        } else {
+            report.push(format!("  {} -> {}-{}", originals.join(", "), start, end));
+        }
+    }
+
+    let fresh_id_count: i64 = merged.iter().map(|((start, end), _)| end - start + 1).sum();
+    report.push(format!("Fresh ids checked: {}", fresh_ids));
+    report.push(format!("Fresh ids in ranges: {}", fresh_id_count));
+    report.join("\n")
+}
+
+// Read ids one line at a time and write whether each is fresh as it goes, so the ids
+// never have to fit in memory. Returns how many of the ids were fresh.
+fn stream_freshness(
+    merged: &[(i64, i64)],
+    reader: impl BufRead,
+    mut writer: impl Write,
+) -> io::Result<usize> {
+    let mut fresh = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let id_str = line.trim();
+        if id_str.is_empty() {
+            continue;
+        }
+        match id_str.parse::<i64>() {
+            Ok(id) if is_fresh(merged, id) => {
+                fresh += 1;
+                writeln!(writer, "{}: fresh", id)?;
+            }
+            Ok(id) => writeln!(writer, "{}: spoiled", id)?,
+            Err(_) => writeln!(writer, "{}: not an id", id_str)?,
+        }
+    }
+    writer.flush()?;
+    Ok(fresh)
+}
+
+// Check ids from a separate file (or stdin when the path is -) against the ranges in the
+// input, printing each id's freshness and returning how many were fresh
+fn solutioner_for_streamed_ids(input: &str, ids_path: &str) -> io::Result<String> {
+    let (fresh_id_ranges, _ids_to_check) = split_input_into_two_arrays(input);
+    let merged = merge_ranges(fresh_id_ranges);
+    let stdout = BufWriter::new(io::stdout().lock());
+    let fresh = if ids_path == "-" {
+        stream_freshness(&merged, io::stdin().lock(), stdout)?
+    } else {
+        stream_freshness(&merged, BufReader::new(File::open(ids_path)?), stdout)?
+    };
+    Ok(fresh.to_string())
+}
+
+// An axis-aligned box with an inclusive range per axis, like 1-5,10-20 in 2D
+type Region = Vec<(i64, i64)>;
+
+// Boxes come first as comma separated ranges (x1-x2,y1-y2 or x1-x2,y1-y2,z1-z2), then a
+// blank line, then points to query written as x,y or x,y,z. Every box and point must
+// have the same number of axes as the first box.
+fn parse_regions_and_points(input: &str) -> Result<(Vec<Region>, Vec<Vec<i64>>), String> {
+    let mut sections = input.split("\n\n");
+    let first_section = sections.next().unwrap_or("");
+    let second_section = sections.next().unwrap_or("");
+
+    let mut regions: Vec<Region> = Vec::new();
+    for line in first_section.lines().filter(|line| !line.trim().is_empty()) {
+        let region = line
+            .split(',')
+            .map(|range| parse_range(range.trim()))
+            .collect::<Result<Region, _>>()
+            .map_err(|reason| format!("Invalid box \"{}\": {}", line, reason))?;
+        if let Some(first) = regions.first()
+            && first.len() != region.len()
+        {
+            return Err(format!("Box \"{}\" does not have {} axes", line, first.len()));
+        }
+        regions.push(region);
+    }
+    let dimensions = regions.first().map_or(0, |region| region.len());
+
+    let mut points = Vec::new();
+    for line in second_section.lines().filter(|line| !line.trim().is_empty()) {
+        let point = line
+            .split(',')
+            .map(|coordinate| coordinate.trim().parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|_| format!("Invalid point \"{}\"", line))?;
+        if point.len() != dimensions {
+            return Err(format!("Point \"{}\" does not have {} axes", line, dimensions));
+        }
+        points.push(point);
+    }
+
+    Ok((regions, points))
+}
+
+fn region_volume(region: &Region) -> i128 {
+    region.iter().map(|(start, end)| (end - start + 1) as i128).product()
+}
+
+fn regions_overlap(a: &Region, b: &Region) -> bool {
+    a.iter().zip(b).all(|(x, y)| x.0 <= y.1 && y.0 <= x.1)
+}
+
+// Volume covered by at least `min_cover` of the boxes. Sweeps along `axis`, cutting it
+// wherever a box starts or ends; between two cuts the same boxes are active, so the slab's
+// volume is its width times the covered volume of the active boxes over the remaining axes.
+// On the last axis this is the same sort-and-merge walk as the 1D ranges, counting how many
+// ranges are open instead of merging them.
+fn covered_volume(regions: &[&Region], axis: usize, min_cover: usize) -> i128 {
+    if regions.len() < min_cover {
+        return 0;
+    }
+    let last_axis = axis + 1 == regions[0].len();
+
+    // Each box opens at its start and closes just past its end
+    let mut events: Vec<(i64, bool, usize)> = Vec::with_capacity(regions.len() * 2);
+    for (idx, region) in regions.iter().enumerate() {
+        events.push((region[axis].0, true, idx));
+        events.push((region[axis].1 + 1, false, idx));
+    }
+    events.sort_unstable();
+
+    let mut active: BTreeSet<usize> = BTreeSet::new();
+    let mut volume = 0;
+    let mut previous_cut = events[0].0;
+    for (cut, opens, idx) in events {
+        if cut > previous_cut && active.len() >= min_cover {
+            let width = (cut - previous_cut) as i128;
+            volume += if last_axis {
+                width
+            } else {
+                let active_regions: Vec<&Region> = active.iter().map(|&i| regions[i]).collect();
+                width * covered_volume(&active_regions, axis + 1, min_cover)
+            };
+        }
+        previous_cut = cut;
+        if opens {
+            active.insert(idx);
+        } else {
+            active.remove(&idx);
+        }
+    }
+    volume
+}
+
+// A point is in the union if any box contains it. Boxes are sorted by where they start on
+// the first axis so only those starting at or before the point need to be checked.
+fn is_point_in_union(sorted_regions: &[Region], point: &[i64]) -> bool {
+    let candidates = sorted_regions.partition_point(|region| region[0].0 <= point[0]);
+    sorted_regions[..candidates].iter().any(|region| {
+        region
+            .iter()
+            .zip(point)
+            .all(|((start, end), coordinate)| start <= coordinate && coordinate <= end)
+    })
+}
+
+// Treat each range line as a 2D or 3D box and report the volume of their union, how much
+// of it is covered more than once, which boxes overlap, and whether each point is inside
+fn solutioner_for_boxes(input: &str) -> String {
+    let (mut regions, points) = match parse_regions_and_points(input) {
+        Ok(parsed) => parsed,
+        Err(e) => return e,
+    };
+    if regions.is_empty() {
+        return "No boxes found".to_string();
+    }
+    regions.sort_unstable();
+
+    let region_refs: Vec<&Region> = regions.iter().collect();
+    let total_volume: i128 = regions.iter().map(region_volume).sum();
+    let union_volume = covered_volume(&region_refs, 0, 1);
+    let shared_volume = covered_volume(&region_refs, 0, 2);
+
+    let mut overlap_counts = vec![0; regions.len()];
+    let mut overlapping_pairs = 0;
+    for i in 0..regions.len() {
+        for j in (i + 1)..regions.len() {
+            // Sorted by start on the first axis, so nothing later can reach back to box i
+            if regions[j][0].0 > regions[i][0].1 {
+                break;
+            }
+            if regions_overlap(&regions[i], &regions[j]) {
+                overlapping_pairs += 1;
+                overlap_counts[i] += 1;
+                overlap_counts[j] += 1;
+            }
+        }
+    }
+
+    let describe = |region: &Region| {
+        let ranges: Vec<String> = region.iter().map(|(s, e)| format!("{}-{}", s, e)).collect();
+        ranges.join(",")
+    };
+    let mut report = vec![
+        format!("Boxes: {} ({}D)", regions.len(), regions[0].len()),
+        format!("Total volume of boxes: {}", total_volume),
+        format!("Union volume: {}", union_volume),
+        format!("Volume covered by more than one box: {}", shared_volume),
+        format!("Excess volume from overlaps: {}", total_volume - union_volume),
+        format!("Overlapping pairs: {}", overlapping_pairs),
+    ];
+    if let Some((most, &count)) = overlap_counts.iter().enumerate().max_by_key(|(_, c)| **c)
+        && count > 0
+    {
+        report.push(format!("Most overlapped box: {} ({} overlaps)", describe(&regions[most]), count));
+    }
+
+    if !points.is_empty() {
+        let mut inside = 0;
+        report.push(format!("Points: {}", points.len()));
+        for point in &points {
+            let coordinates: Vec<String> = point.iter().map(|c| c.to_string()).collect();
+            if is_point_in_union(&regions, point) {
+                inside += 1;
+                report.push(format!("  {}: inside", coordinates.join(",")));
+            } else {
+                report.push(format!("  {}: outside", coordinates.join(",")));
+            }
+        }
+        report.push(format!("Points inside: {}", inside));
+    }
+    report.join("\n")
+}
 
 /// Main function to read input file, process it, and write to output file
 /// Uses command line arguments for input and which part you want to solve 
@@ -100,9 +455,12 @@ fn main() -> io::Result<()> {
     let mut contents = String::new();
     input_file.read_to_string(&mut contents)?;
 
-    let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+    let processed = match (&args.ids, args.part) {
+        (Some(ids_path), _) => solutioner_for_streamed_ids(&contents, ids_path)?,
+        (None, _) if args.diagnose => solutioner_for_diagnostics(&contents),
+        (None, _) if args.boxes => solutioner_for_boxes(&contents),
+        (None, 1) => solutioner_for_part_1(&contents),
+        (None, 2) => solutioner_for_part_2(&contents),
         _ => "Invalid part specified".to_string(),
     };
 
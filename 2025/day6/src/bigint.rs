@@ -0,0 +1,239 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Each limb holds nine decimal digits so printing never needs a base conversion
+const BASE: u64 = 1_000_000_000;
+
+// A signed integer of any size, stored as base 10^9 limbs from least to most significant.
+// Zero has no limbs and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+        BigInt { negative: value < 0, limbs }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some((most_significant, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", most_significant)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+            (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let total = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push((total % BASE) as u32);
+        carry = total / BASE;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// a - b where |a| >= |b|
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut value = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if value < 0 {
+            value += BASE as i64;
+            borrow = 1;
+        }
+        difference.push(value as u32);
+    }
+    trim(&mut difference);
+    difference
+}
+
+fn multiply_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0u64; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let total = product[i + j] + x as u64 * y as u64 + carry;
+            product[i + j] = total % BASE;
+            carry = total / BASE;
+        }
+        product[i + b.len()] += carry;
+    }
+    let mut limbs: Vec<u32> = product.into_iter().map(|limb| limb as u32).collect();
+    trim(&mut limbs);
+    limbs
+}
+
+// Schoolbook long division of magnitudes, one limb at a time. Each quotient limb is
+// found by binary search for the largest q with divisor * q <= the running remainder.
+fn divide_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for i in (0..a.len()).rev() {
+        remainder.insert(0, a[i]);
+        trim(&mut remainder);
+        let (mut low, mut high) = (0u64, BASE - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            let candidate = multiply_magnitudes(b, &[mid as u32]);
+            if compare_magnitudes(&candidate, &remainder) == Ordering::Greater {
+                high = mid - 1;
+            } else {
+                low = mid;
+            }
+        }
+        if low > 0 {
+            remainder = subtract_magnitudes(&remainder, &multiply_magnitudes(b, &[low as u32]));
+        }
+        quotient[i] = low as u32;
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, limbs: Vec<u32>) -> Self {
+        let negative = negative && !limbs.is_empty();
+        BigInt { negative, limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // Roughly how many decimal digits the magnitude has, 0 for zero
+    pub fn log10_magnitude(&self) -> f64 {
+        match self.limbs.split_last() {
+            None => 0.0,
+            Some((&most_significant, rest)) => {
+                (most_significant as f64).log10() + 9.0 * rest.len() as f64
+            }
+        }
+    }
+
+    pub fn to_u32(&self) -> Option<u32> {
+        match self.limbs.as_slice() {
+            _ if self.negative => None,
+            [] => Some(0),
+            [low] => Some(*low),
+            [low, high] => u32::try_from(*high as u64 * BASE + *low as u64).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitudes(&self.limbs, &other.limbs));
+        }
+        match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                subtract_magnitudes(&other.limbs, &self.limbs),
+            ),
+            _ => BigInt::from_parts(self.negative, subtract_magnitudes(&self.limbs, &other.limbs)),
+        }
+    }
+
+    pub fn negate(&self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.limbs.clone())
+    }
+
+    pub fn subtract(&self, other: &BigInt) -> BigInt {
+        self.add(&other.negate())
+    }
+
+    pub fn multiply(&self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            multiply_magnitudes(&self.limbs, &other.limbs),
+        )
+    }
+
+    // Quotient rounded toward zero and the remainder with the sign of self, like i64's / and %.
+    // None when dividing by zero.
+    pub fn divide_truncating(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = divide_magnitudes(&self.limbs, &other.limbs);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, exponent: u32) -> BigInt {
+        let mut result = BigInt::from(1);
+        let mut base = self.clone();
+        let mut exponent = exponent;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base);
+            }
+        }
+        result
+    }
+}
@@ -1,7 +1,12 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs::File;
 use std::io::{self, Read, Write};
-use polars::prelude::*;
+
+mod bigint;
+#[cfg(feature = "polars")]
+mod polars_worksheet;
+
+use bigint::BigInt;
 
 #[derive(Parser)]
 #[command(name = "solutions_runner")]
@@ -14,141 +19,357 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// How / rounds when the division is not exact
+    #[arg(short, long, value_enum, default_value_t = Rounding::Truncate)]
+    rounding: Rounding,
+
+    /// How the digits of each number are laid out (defaults to left-to-right for part 1
+    /// and top-to-bottom for part 2)
+    #[arg(short, long, value_enum)]
+    order: Option<DigitOrder>,
+
+    /// Evaluate the worksheet with a polars lazy group-by (+ and * only)
+    #[cfg(feature = "polars")]
+    #[arg(long)]
+    lazy: bool,
+
+    /// Write the parsed worksheet to a .parquet or .csv file
+    #[cfg(feature = "polars")]
+    #[arg(long)]
+    export: Option<String>,
 }
 
-// Input is space separated integer values except for last line which is space
-// separated strings which are one character each and are operators like +, -, *, /
-fn parse_string_into_dataframe_and_list_of_operators(
-    input: &str,
-) -> PolarsResult<(DataFrame, Vec<String>)> {
-    let lines: Vec<&str> = input.lines().filter(|l| !l.trim().is_empty()).collect();
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Rounding {
+    /// Round toward zero
+    Truncate,
+    /// Round toward negative infinity
+    Floor,
+    /// Round toward positive infinity
+    Ceil,
+}
 
-    let numeric_lines = &lines[..lines.len() - 1];
-    let operator_line = lines[lines.len() - 1];
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum DigitOrder {
+    /// Each row of a problem is a number read left to right
+    LeftToRight,
+    /// Each column of a problem is a number with its most significant digit at the top,
+    /// and the columns are read from right to left as cephalopods do
+    TopToBottom,
+    /// Each row of a problem is a number with its most significant digit on the right
+    RightToLeft,
+}
 
-    let mut columns: Vec<Vec<i64>> = Vec::new();
+// Operators that can sit at the bottom of a worksheet column. Each one is applied to the
+// column's numbers from top to bottom, so - gives a - b - c and ^ gives (a ^ b) ^ c.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Min,
+    Max,
+    Power,
+}
 
-    for line in numeric_lines {
-        let nums: Vec<i64> = line
-            .split_whitespace()
-            .filter_map(|s| s.parse().ok())
-            .collect();
+impl Operator {
+    fn parse(symbol: &str) -> Option<Operator> {
+        match symbol {
+            "+" => Some(Operator::Add),
+            "-" => Some(Operator::Subtract),
+            "*" => Some(Operator::Multiply),
+            "/" => Some(Operator::Divide),
+            "%" => Some(Operator::Remainder),
+            "min" => Some(Operator::Min),
+            "max" => Some(Operator::Max),
+            "^" => Some(Operator::Power),
+            _ => None,
+        }
+    }
 
-        if columns.is_empty() {
-            columns = vec![Vec::new(); nums.len()];
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Remainder => "%",
+            Operator::Min => "min",
+            Operator::Max => "max",
+            Operator::Power => "^",
         }
+    }
 
-        for (col_idx, &num) in nums.iter().enumerate() {
-            columns[col_idx].push(num);
+    // Integer division rounded the requested way, or None on division by zero or overflow
+    fn divide(a: i64, b: i64, rounding: Rounding) -> Option<i64> {
+        let quotient = a.checked_div(b)?;
+        let remainder = a.checked_rem(b)?;
+        if remainder == 0 {
+            return Some(quotient);
+        }
+        let exact_is_negative = (remainder < 0) != (b < 0);
+        match rounding {
+            Rounding::Truncate => Some(quotient),
+            Rounding::Floor if exact_is_negative => quotient.checked_sub(1),
+            Rounding::Ceil if !exact_is_negative => quotient.checked_add(1),
+            _ => Some(quotient),
         }
     }
 
-    // Parse operators
-    let operators: Vec<String> = operator_line
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+    // Combine the running value with the next number, explaining what went wrong if it can't be done
+    fn apply(&self, a: i64, b: i64, rounding: Rounding) -> Result<i64, ApplyError> {
+        let overflow = ApplyError::Overflow;
+        match self {
+            Operator::Add => a.checked_add(b).ok_or(overflow),
+            Operator::Subtract => a.checked_sub(b).ok_or(overflow),
+            Operator::Multiply => a.checked_mul(b).ok_or(overflow),
+            Operator::Divide | Operator::Remainder if b == 0 => Err(DIVISION_BY_ZERO),
+            Operator::Divide => Operator::divide(a, b, rounding).ok_or(overflow),
+            // The remainder matches the rounding used for division: a = b * (a / b) + a % b
+            Operator::Remainder => {
+                let quotient = Operator::divide(a, b, rounding).ok_or(overflow)?;
+                b.checked_mul(quotient)
+                    .and_then(|product| a.checked_sub(product))
+                    .ok_or(overflow)
+            }
+            Operator::Min => Ok(a.min(b)),
+            Operator::Max => Ok(a.max(b)),
+            Operator::Power => {
+                let exponent = u32::try_from(b).map_err(|_| BAD_EXPONENT)?;
+                a.checked_pow(exponent).ok_or(overflow)
+            }
+        }
+    }
 
-    // Create DataFrame
-    let series_vec: Vec<Column> = columns
-        .into_iter()
-        .enumerate()
-        .map(|(i, col)| Series::new(format!("col_{}", i + 1).into(), col).into())
-        .collect();
+    // BigInt version of divide, which can't overflow
+    fn divide_big(a: &BigInt, b: &BigInt, rounding: Rounding) -> Result<BigInt, ApplyError> {
+        let (quotient, remainder) = a.divide_truncating(b).ok_or(DIVISION_BY_ZERO)?;
+        if remainder.is_zero() {
+            return Ok(quotient);
+        }
+        let exact_is_negative = remainder.is_negative() != b.is_negative();
+        Ok(match rounding {
+            Rounding::Floor if exact_is_negative => quotient.subtract(&BigInt::from(1)),
+            Rounding::Ceil if !exact_is_negative => quotient.add(&BigInt::from(1)),
+            _ => quotient,
+        })
+    }
 
-    let df = DataFrame::new(series_vec)?;
-    Ok((df, operators))
+    // The same as apply, for values that no longer fit in an i64
+    fn apply_big(&self, a: &BigInt, b: &BigInt, rounding: Rounding) -> Result<BigInt, ApplyError> {
+        match self {
+            Operator::Add => Ok(a.add(b)),
+            Operator::Subtract => Ok(a.subtract(b)),
+            Operator::Multiply => Ok(a.multiply(b)),
+            Operator::Divide => Operator::divide_big(a, b, rounding),
+            Operator::Remainder => {
+                let quotient = Operator::divide_big(a, b, rounding)?;
+                Ok(a.subtract(&b.multiply(&quotient)))
+            }
+            Operator::Min => Ok(a.min(b).clone()),
+            Operator::Max => Ok(a.max(b).clone()),
+            Operator::Power => {
+                let exponent = b.to_u32().ok_or(BAD_EXPONENT)?;
+                // Check the size up front, a huge power would take forever to multiply out
+                if a.log10_magnitude() * exponent as f64 > MAX_RESULT_DIGITS {
+                    return Err(RESULT_TOO_LARGE);
+                }
+                Ok(a.pow(exponent))
+            }
+        }
+    }
 }
 
-// Given input like: 
-// 123 328  51 64 
-//  45 64  387 23 
-//   6 98  215 314
-// *   +   *   + 
-// Take the numbers in each column and apply the operator at the bottom to them
-fn solutioner_for_part_1(input: &str) -> PolarsResult<String> {
-    let (df, operators) = parse_string_into_dataframe_and_list_of_operators(input)?;
-    let mut total = 0i64;
-    for (i, operator) in operators.iter().enumerate() {
-        let column = df
-            .column(&format!("col_{}", i + 1))?
-            .i64()?;
-        let numbers_to_perform_operation_on: Vec<i64> = column
-            .into_iter()
-            .filter_map(|v| v)
-            .collect();
-        let column_total = match operator.as_str() {
-            "+" => numbers_to_perform_operation_on.iter().sum(),
-            "*" => numbers_to_perform_operation_on.iter().product(),
-            _ => 0,
-        };
-        total += column_total;
+// Why an operator couldn't be applied. Overflow only means the i64 ran out of room,
+// so the work can be redone with BigInt; anything else is a problem with the worksheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ApplyError {
+    Overflow,
+    Invalid(&'static str),
+}
+
+const DIVISION_BY_ZERO: ApplyError = ApplyError::Invalid("division by zero");
+const BAD_EXPONENT: ApplyError = ApplyError::Invalid("exponent must be a non-negative u32");
+const RESULT_TOO_LARGE: ApplyError = ApplyError::Invalid("result too large");
+
+// Largest number of decimal digits a power may produce
+const MAX_RESULT_DIGITS: f64 = 10_000.0;
+
+// One block of the worksheet: the operator written under it, its numbers in reading
+// order, and the character column the block starts in so errors can point at it
+#[derive(Debug, Clone, PartialEq)]
+struct Problem {
+    op: Operator,
+    operands: Vec<i64>,
+    column: usize,
+}
+
+impl Problem {
+    fn error(&self, reason: &str) -> String {
+        format!("Column {} ({}): {}", self.column, self.op.symbol(), reason)
     }
-    Ok(total.to_string())
 }
 
-// Given input like: 
-// 123 328  51 64 
-//  45 64  387 23 
+// Read the digits of one number, or nothing if the text is blank
+fn parse_operand(text: &str, column: usize) -> Result<Option<i64>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse()
+        .map(Some)
+        .map_err(|_| format!("Column {}: \"{}\" is not a number", column, text))
+}
+
+// Split a worksheet into problems. The last non-blank line holds the operators and every
+// line above it holds digits. Lines may have different lengths and are treated as if padded
+// with spaces; a column that is a space in every line separates one problem from the next.
+// Within a problem the operator can sit anywhere on the bottom line and the digits are
+// read in the given order, for example with left-to-right
+// 123 328  51 64
+//  45 64  387 23
 //   6 98  215 314
-// *   +   *   + 
-// Take the numbers in each column and transform them so that a new number is created
-// using the digit at each number so the last column becomes 4, 431, and 623
-// or for another example the 2nd column becomes 8, 248, and 369 then 
-// perform the operation in the final row on those new numbers.
-fn solutioner_for_part_2(input: &str) -> PolarsResult<String> {
-    let mut total = 0i64;
-    let char_matrix: Vec<Vec<char>> = input
+// *   +   *   +
+// the last problem is 64 + 23 + 314, while reading top-to-bottom it is 4 + 431 + 623
+// and the 2nd problem is 8 + 248 + 369.
+fn parse_problems(input: &str, order: DigitOrder) -> Result<Vec<Problem>, String> {
+    let lines: Vec<Vec<char>> = input
         .lines()
+        .filter(|line| !line.trim().is_empty())
         .map(|line| line.chars().collect())
         .collect();
-    let operators_row = char_matrix
-        .last()
-        .ok_or_else(|| PolarsError::ComputeError("No operator row found".into()))?;
-    // create a data sructure that can hold a list of operands and a list of numbers associated with each operand
-    let mut operands_and_numbers: Vec<(String, Vec<String>)> = Vec::new(); 
-    for (col_idx, &operator) in operators_row.iter().enumerate() {
-        if !operator.is_whitespace() {
-            operands_and_numbers.push((operator.to_string(), Vec::new()));
-        }
-        let mut new_number_str = String::new();
-        for row_idx in 0..(char_matrix.len() - 1) {
-            let digit_char = char_matrix[row_idx]
-                .get(col_idx)
-                .ok_or_else(|| PolarsError::ComputeError("Index out of bounds".into()))?;
-            if digit_char.is_digit(10) {
-                new_number_str.push(*digit_char);
+    let (operator_row, digit_rows) = lines
+        .split_last()
+        .ok_or_else(|| "No operator row found".to_string())?;
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let char_at = |line: &[char], col: usize| line.get(col).copied().unwrap_or(' ');
+
+    let is_separator = |col: usize| lines.iter().all(|line| char_at(line, col) == ' ');
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut block_start: Option<usize> = None;
+    for col in 0..=width {
+        match (block_start, col == width || is_separator(col)) {
+            (None, false) => block_start = Some(col),
+            (Some(start), true) => {
+                blocks.push((start, col));
+                block_start = None;
             }
+            _ => {}
         }
-        operands_and_numbers
-            .last_mut()
-            .ok_or_else(|| PolarsError::ComputeError("No operand found to associate number with".into()))?
-            .1
-            .push(new_number_str.clone());
-    }
-    //println!("Operands map: {:?}", operands_and_numbers);
-    // get the total by performing the operation on each list of numbers and summing the result
-    for (operator, numbers) in operands_and_numbers {
-        let nums_as_i64: Vec<i64> = numbers
-            .iter()
-            .filter_map(|s| s.parse().ok())
-            .collect();
-        let column_total = match operator.as_str() {
-            "+" => nums_as_i64.iter().sum(),
-            "*" => nums_as_i64.iter().product(),
-            _ => 0,
+    }
+
+    let mut problems = Vec::new();
+    for (start, end) in blocks {
+        let column = start + 1;
+        let text_in_block = |line: &[char]| -> String {
+            (start..end).map(|col| char_at(line, col)).collect::<String>().trim().to_string()
         };
-        total += column_total;
-        // println!(
-        //     "Operator: {}, Numbers: {:?}, Column total: {}",
-        //     operator, nums_as_i64, column_total
-        // );
+        let symbol = text_in_block(operator_row);
+        let op = match Operator::parse(&symbol) {
+            Some(op) => op,
+            None if symbol.is_empty() => return Err(format!("Column {}: no operator", column)),
+            None => return Err(format!("Column {} ({}): unknown operator", column, symbol)),
+        };
+
+        let mut operands = Vec::new();
+        match order {
+            DigitOrder::LeftToRight | DigitOrder::RightToLeft => {
+                for row in digit_rows {
+                    let mut text = text_in_block(row);
+                    if order == DigitOrder::RightToLeft {
+                        text = text.chars().rev().collect();
+                    }
+                    operands.extend(parse_operand(&text, column)?);
+                }
+            }
+            DigitOrder::TopToBottom => {
+                for col in (start..end).rev() {
+                    let text: String = digit_rows
+                        .iter()
+                        .map(|row| char_at(row, col))
+                        .filter(|c| *c != ' ')
+                        .collect();
+                    operands.extend(parse_operand(&text, col + 1)?);
+                }
+            }
+        }
+        problems.push(Problem { op, operands, column });
+    }
+    Ok(problems)
+}
+
+// Apply the operator written under a problem to its numbers, in i64 while everything fits
+// and otherwise starting over with BigInt so the answer is always exact.
+// Sums of nothing are 0 and products of nothing are 1, but the other operators need a number.
+fn evaluate_problem(problem: &Problem, rounding: Rounding) -> Result<BigInt, String> {
+    let (first, rest) = match (problem.op, problem.operands.split_first()) {
+        (_, Some((first, rest))) => (*first, rest),
+        (Operator::Add, None) => return Ok(BigInt::from(0)),
+        (Operator::Multiply, None) => return Ok(BigInt::from(1)),
+        (_, None) => return Err(problem.error("no numbers to apply the operator to")),
+    };
+    let small = rest
+        .iter()
+        .try_fold(first, |value, &number| problem.op.apply(value, number, rounding));
+    let result = match small {
+        Ok(value) => Ok(BigInt::from(value)),
+        Err(ApplyError::Overflow) => rest.iter().try_fold(BigInt::from(first), |value, &number| {
+            problem.op.apply_big(&value, &BigInt::from(number), rounding)
+        }),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| match e {
+        ApplyError::Overflow => problem.error("overflow"),
+        ApplyError::Invalid(reason) => problem.error(reason),
+    })
+}
+
+// Perform the operation on each problem's numbers and sum the results
+fn evaluate_worksheet(problems: &[Problem], rounding: Rounding) -> Result<BigInt, String> {
+    let mut total = BigInt::from(0);
+    for problem in problems {
+        total = total.add(&evaluate_problem(problem, rounding)?);
+    }
+    Ok(total)
+}
+
+// Export the worksheet if asked to, then work out the grand total natively or,
+// with --lazy, through a polars group-by as long as it can't overflow i64
+#[cfg(feature = "polars")]
+fn total_worksheet(problems: &[Problem], args: &Args) -> Result<String, String> {
+    if let Some(path) = &args.export {
+        polars_worksheet::export(problems, path).map_err(|e| e.to_string())?;
+    }
+    if args.lazy {
+        match polars_worksheet::evaluate_lazy(problems).map_err(|e| e.to_string())? {
+            Some(total) => return Ok(total.to_string()),
+            None => println!("Worksheet could overflow i64 in polars, evaluating it exactly instead"),
+        }
     }
-    Ok(total.to_string())
+    Ok(evaluate_worksheet(problems, args.rounding)?.to_string())
 }
 
- 
+// Work out the grand total of the worksheet
+#[cfg(not(feature = "polars"))]
+fn total_worksheet(problems: &[Problem], args: &Args) -> Result<String, String> {
+    Ok(evaluate_worksheet(problems, args.rounding)?.to_string())
+}
 
+// Take the numbers in each column, reading each row left to right,
+// and apply the operator at the bottom to them
+fn solutioner_for_part_1(input: &str, args: &Args) -> Result<String, String> {
+    let problems = parse_problems(input, args.order.unwrap_or(DigitOrder::LeftToRight))?;
+    total_worksheet(&problems, args)
+}
+
+// Take the numbers in each column, reading each column top to bottom as described in
+// parse_problems, then perform the operation in the final row on those new numbers.
+fn solutioner_for_part_2(input: &str, args: &Args) -> Result<String, String> {
+    let problems = parse_problems(input, args.order.unwrap_or(DigitOrder::TopToBottom))?;
+    total_worksheet(&problems, args)
+}
 
 /// Main function to read input file, process it, and write to output file
 /// Uses command line arguments for input and which part you want to solve 
@@ -162,11 +383,11 @@ fn main() -> io::Result<()> {
     input_file.read_to_string(&mut contents)?;
 
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
-        _ => Err(PolarsError::ComputeError("Invalid part specified".into())),
+        1 => solutioner_for_part_1(&contents, &args),
+        2 => solutioner_for_part_2(&contents, &args),
+        _ => Err("Invalid part specified".to_string()),
     }
-    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    .map_err(io::Error::other)?;
 
     let mut output_file = File::create(&output)?;
     output_file.write_all(processed.as_bytes())?;
@@ -175,3 +396,47 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(worksheet: &str) -> String {
+        let problems = parse_problems(worksheet, DigitOrder::LeftToRight).unwrap();
+        evaluate_worksheet(&problems, Rounding::Truncate).unwrap().to_string()
+    }
+
+    #[test]
+    fn product_that_overflows_i64_is_exact() {
+        // A dozen 4-digit numbers multiply to far more than i64::MAX
+        let worksheet = "9999\n".repeat(12) + "*\n";
+        assert_eq!(total(&worksheet), "998800659780049492080923920804949780006599880001");
+    }
+
+    #[test]
+    fn grand_total_that_overflows_i64_is_exact() {
+        let worksheet = "9000000000000000000 9000000000000000000 5\n+                   +                   *\n";
+        assert_eq!(total(worksheet), "18000000000000000005");
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn lazy_total_counts_empty_blocks_like_native() {
+        let problems = parse_problems(" \n+ *\n", DigitOrder::LeftToRight).unwrap();
+        let lazy = polars_worksheet::evaluate_lazy(&problems).unwrap().unwrap();
+        assert_eq!(lazy.to_string(), total(" \n+ *\n"));
+    }
+
+    #[test]
+    fn huge_power_is_rejected() {
+        let problems = parse_problems("2\n99999999\n^\n", DigitOrder::LeftToRight).unwrap();
+        let error = evaluate_worksheet(&problems, Rounding::Truncate).unwrap_err();
+        assert!(error.contains("result too large"), "{}", error);
+    }
+
+    #[test]
+    fn division_that_overflows_i64_is_exact() {
+        let worksheet = "-9223372036854775808\n                  -1\n/\n";
+        assert_eq!(total(worksheet), "9223372036854775808");
+    }
+}
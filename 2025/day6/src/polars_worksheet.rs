@@ -0,0 +1,91 @@
+use crate::{Operator, Problem};
+use polars::prelude::*;
+use std::fs::File;
+
+// Lay the worksheet out long-form with one row per number: which problem it belongs to,
+// the operator for that problem, its row within the problem and its value
+pub fn to_dataframe(problems: &[Problem]) -> PolarsResult<DataFrame> {
+    let mut indexes: Vec<u32> = Vec::new();
+    let mut operators: Vec<&str> = Vec::new();
+    let mut rows: Vec<u32> = Vec::new();
+    let mut values: Vec<i64> = Vec::new();
+    for (idx, problem) in problems.iter().enumerate() {
+        for (row, &value) in problem.operands.iter().enumerate() {
+            indexes.push(idx as u32 + 1);
+            operators.push(problem.op.symbol());
+            rows.push(row as u32 + 1);
+            values.push(value);
+        }
+    }
+    df!(
+        "problem" => indexes,
+        "operator" => operators,
+        "row" => rows,
+        "value" => values,
+    )
+}
+
+// Write the parsed worksheet to a Parquet or CSV file, picked by the file extension
+pub fn export(problems: &[Problem], path: &str) -> PolarsResult<()> {
+    let mut df = to_dataframe(problems)?;
+    let file = File::create(path)?;
+    if path.ends_with(".parquet") {
+        ParquetWriter::new(file).finish(&mut df)?;
+    } else if path.ends_with(".csv") {
+        CsvWriter::new(file).finish(&mut df)?;
+    } else {
+        polars_bail!(ComputeError: "Export path {} must end in .parquet or .csv", path);
+    }
+    Ok(())
+}
+
+// An upper bound on the size of every partial sum, product and total polars works out for
+// these problems, or None if the bound doesn't fit in an i64
+fn magnitude_bound(problems: &[Problem]) -> Option<i64> {
+    problems.iter().try_fold(0i64, |total, problem| {
+        let mut magnitudes = problem.operands.iter().map(|value| value.checked_abs());
+        let bound = if problem.op == Operator::Multiply {
+            magnitudes.try_fold(1i64, |product, magnitude| product.checked_mul(magnitude?))
+        } else {
+            magnitudes.try_fold(0i64, |sum, magnitude| sum.checked_add(magnitude?))
+        }?;
+        total.checked_add(bound)
+    })
+}
+
+// Group the numbers by problem and let polars sum or multiply each group lazily.
+// Only + and * have polars aggregations, so any other operator is an error here.
+// Polars works in i64 and wraps silently, so this gives None rather than run it when
+// any part of the worksheet could overflow.
+pub fn evaluate_lazy(problems: &[Problem]) -> PolarsResult<Option<i64>> {
+    for problem in problems {
+        if problem.op != Operator::Add && problem.op != Operator::Multiply {
+            polars_bail!(ComputeError: "{}", problem.error("only + and * can be evaluated lazily"));
+        }
+    }
+    if magnitude_bound(problems).is_none() {
+        return Ok(None);
+    }
+
+    let totals = to_dataframe(problems)?
+        .lazy()
+        .group_by([col("problem"), col("operator")])
+        .agg([
+            col("value").sum().alias("sum"),
+            col("value").product().alias("product"),
+        ])
+        .select([when(col("operator").eq(lit("*")))
+            .then(col("product"))
+            .otherwise(col("sum"))
+            .alias("total")])
+        .collect()?;
+
+    // A block with no numbers has no rows to group, but like the native evaluation an
+    // empty product still counts as 1 (and an empty sum as 0)
+    let empty_products = problems
+        .iter()
+        .filter(|problem| problem.op == Operator::Multiply && problem.operands.is_empty())
+        .count() as i64;
+
+    Ok(Some(totals.column("total")?.i64()?.sum().unwrap_or(0) + empty_products))
+}
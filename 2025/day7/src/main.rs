@@ -1,4 +1,5 @@
 use clap::Parser;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, Read, Write};
 
@@ -13,6 +14,45 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// Run the general beam simulation with mirrors, splitters and absorbers instead of a part
+    #[arg(short, long)]
+    simulate: bool,
+
+    /// Print the grid with the beams drawn in and say which splitters are hit,
+    /// never reached or shadowed (part 1)
+    #[arg(long)]
+    splitters: bool,
+
+    /// Print a log-scaled heatmap of how many timelines pass through each cell (part 2)
+    #[arg(long)]
+    heatmap: bool,
+
+    /// Write the timeline count for every cell to this CSV file (part 2)
+    #[arg(long)]
+    heatmap_csv: Option<String>,
+
+    /// Write the heatmap as a PPM image to this file (part 2)
+    #[arg(long)]
+    heatmap_ppm: Option<String>,
+
+    /// Width and height in pixels of each cell in the PPM image
+    #[arg(long, default_value_t = 4)]
+    pixel_size: usize,
+
+    /// Send the beam left at each splitter with this probability (like 1/3 or 0.25) and
+    /// right otherwise, printing where the beam leaves the grid
+    #[arg(long)]
+    probability: Option<String>,
+
+    /// File of row,column,p lines giving individual splitters their own probability of going left
+    #[arg(long)]
+    probability_map: Option<String>,
+
+    /// Keep the probabilities as exact fractions instead of floats, splitting 1/2 each way
+    /// unless --probability says otherwise
+    #[arg(long)]
+    exact: bool,
 }
 // Converts a string of characters into a 2D vector of strings
 fn convert_string_into_two_d_vector(input: &str) -> Vec<Vec<String>> {
@@ -45,11 +85,11 @@ fn solutioner_for_part_1(input: &str) -> String {
     let mut beam_positions: Vec<usize> = vec![current_beam_start];
     let width = grid[0].len();
 
-    for i in 0..grid.len() {
+    for row in &grid {
         let mut new_beam_positions: Vec<usize> = Vec::new();
 
         for &pos in &beam_positions {
-            if grid[i][pos] == "^" {
+            if row[pos] == "^" {
                 // Split beam into left 
                 if pos > 0 {
                     new_beam_positions.push(pos - 1);
@@ -76,26 +116,130 @@ fn solutioner_for_part_1(input: &str) -> String {
 }
 
 
-// Build a grid and make paths from the S counter spliting the beam when 
-// encountering ^ characters. Count the number of unique paths that are created
-// by the splitting of the beams. Where a path is started at S and ends 
-// when the path gets to the edge of the grid.
-fn solutioner_for_part_2(input: &str) -> String {
-    let grid = convert_string_into_two_d_vector(input);
+// Follow the beams down the grid the same way part 1 does, keeping every row:
+// beams[r][c] is true when a beam enters row r in column c. The extra last row holds
+// the columns the beams leave the grid from.
+fn trace_beam_rows(grid: &[Vec<String>]) -> Option<Vec<Vec<bool>>> {
+    let start_x = grid.first()?.iter().position(|cell| cell == "S")?;
+    let width = grid[0].len();
 
-    let start_x = match grid[0].iter().position(|cell| cell == "S") {
-        Some(x) => x,
-        None => return "No starting position found".to_string(),
+    let mut beams: Vec<Vec<bool>> = Vec::with_capacity(grid.len() + 1);
+    let mut first_row = vec![false; width];
+    first_row[start_x] = true;
+    beams.push(first_row);
+
+    for row in grid {
+        let entering = beams.last().unwrap();
+        let mut leaving = vec![false; width];
+        for pos in (0..width).filter(|&pos| entering[pos]) {
+            if row[pos] == "^" {
+                if pos > 0 {
+                    leaving[pos - 1] = true;
+                }
+                if pos + 1 < width {
+                    leaving[pos + 1] = true;
+                }
+            } else {
+                leaving[pos] = true;
+            }
+        }
+        beams.push(leaving);
+    }
+
+    Some(beams)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SplitterState {
+    // A beam arrives at the splitter
+    Hit,
+    // No beam ever travels down this column above the splitter
+    NeverReached,
+    // A beam travels down this column higher up but is split away before getting here
+    Shadowed,
+}
+
+impl SplitterState {
+    fn label(self) -> &'static str {
+        match self {
+            SplitterState::Hit => "Hit",
+            SplitterState::NeverReached => "Never reached",
+            SplitterState::Shadowed => "Shadowed",
+        }
+    }
+}
+
+// Every ^ in the grid as (row, column, state), in reading order
+fn classify_splitters(grid: &[Vec<String>], beams: &[Vec<bool>]) -> Vec<(usize, usize, SplitterState)> {
+    let mut splitters = Vec::new();
+    for (r, row) in grid.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            if cell != "^" {
+                continue;
+            }
+            let state = if beams[r][c] {
+                SplitterState::Hit
+            } else if beams[..r].iter().any(|entering| entering[c]) {
+                SplitterState::Shadowed
+            } else {
+                SplitterState::NeverReached
+            };
+            splitters.push((r, c, state));
+        }
+    }
+    splitters
+}
+
+// Draw the grid the way the puzzle does, with | wherever a beam leaves an empty cell
+fn render_beams(grid: &[Vec<String>], beams: &[Vec<bool>]) -> String {
+    let mut rendered = String::new();
+    for (row, leaving) in grid.iter().zip(&beams[1..]) {
+        for (cell, &beam) in row.iter().zip(leaving) {
+            if beam && cell == "." {
+                rendered.push('|');
+            } else {
+                rendered.push_str(cell);
+            }
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+// The rendered grid followed by every splitter grouped by state with its (row, column)
+fn splitter_report(input: &str) -> String {
+    let grid = convert_string_into_two_d_vector(input);
+    let Some(beams) = trace_beam_rows(&grid) else {
+        return "No starting position found".to_string();
     };
+    let splitters = classify_splitters(&grid, &beams);
+
+    let mut report = render_beams(&grid, &beams);
+    for state in [SplitterState::Hit, SplitterState::NeverReached, SplitterState::Shadowed] {
+        let coordinates: Vec<String> = splitters
+            .iter()
+            .filter(|splitter| splitter.2 == state)
+            .map(|(r, c, _)| format!("({}, {})", r, c))
+            .collect();
+        report.push_str(&format!("{} ({}): {}\n", state.label(), coordinates.len(), coordinates.join(" ")));
+    }
+    report
+}
 
+// Make paths from the S counter spliting the beam when encountering ^ characters,
+// keeping every row: ways[r][c] is number of paths that are in column c once they
+// have passed row r.
+fn count_timelines_per_row(grid: &[Vec<String>]) -> Option<Vec<Vec<u128>>> {
+    let start_x = grid.first()?.iter().position(|cell| cell == "S")?;
     let width = grid[0].len();
-    let height = grid.len();
 
-    // DP over rows: ways[r][c] is number of paths that reach column c on row r.
-    let mut current_counts = vec![0u128; width];
-    current_counts[start_x] = 1;
+    let mut ways: Vec<Vec<u128>> = Vec::with_capacity(grid.len());
+    let mut first_counts = vec![0u128; width];
+    first_counts[start_x] = 1;
+    ways.push(first_counts);
 
-    for curent_row in 1..height {
+    for current_row in &grid[1..] {
+        let current_counts = ways.last().unwrap();
         let mut next_counts = vec![0u128; width];
 
         // Update counts for the next row based on current row
@@ -104,7 +248,8 @@ fn solutioner_for_part_2(input: &str) -> String {
                 continue;
             }
 
-            if grid[current_row][pos] == "^" {
+            // Rows shorter than the first are treated as empty past their end
+            if current_row.get(pos).is_some_and(|cell| cell == "^") {
                 if pos > 0 {
                     next_counts[pos - 1] += *count;
                 }
@@ -116,12 +261,578 @@ fn solutioner_for_part_2(input: &str) -> String {
             }
         }
 
-        current_counts = next_counts;
+        ways.push(next_counts);
     }
 
-    current_counts.iter().sum::<u128>().to_string()
+    Some(ways)
+}
+
+// Where a count sits between 0 and the largest count on a log scale, from 0.0 to 1.0,
+// so rows with a handful of paths still show up next to rows with trillions
+fn log_scale(count: u128, max: u128) -> f64 {
+    if max == 0 {
+        return 0.0;
+    }
+    (count as f64).ln_1p() / (max as f64).ln_1p()
+}
+
+// How many paths pass through a cell. ways[r] counts the paths leaving row r, which is
+// always 0 under a splitter, so splitters use the paths arriving from the row above.
+fn paths_through_cell(grid: &[Vec<String>], ways: &[Vec<u128>], r: usize, c: usize) -> u128 {
+    if r > 0 && grid[r].get(c).is_some_and(|cell| cell == "^") {
+        ways[r - 1][c]
+    } else {
+        ways[r][c]
+    }
+}
+
+// Shade each cell by its log-scaled path count, from a space for no paths up to @ for the
+// most. Splitters nobody reaches stay as ^ so the layout is still recognisable.
+fn render_heatmap_ascii(grid: &[Vec<String>], ways: &[Vec<u128>]) -> String {
+    let shades: Vec<char> = " .:-=+*#%@".chars().collect();
+    let max = ways.iter().flatten().copied().max().unwrap_or(0);
+    let mut heatmap = String::new();
+    for (r, row) in grid.iter().enumerate() {
+        for c in 0..ways[r].len() {
+            let count = paths_through_cell(grid, ways, r, c);
+            if count == 0 && row.get(c).is_some_and(|cell| cell == "^") {
+                heatmap.push('^');
+            } else if count == 0 {
+                heatmap.push(' ');
+            } else {
+                // Anything with a path gets at least the faintest shade
+                let level = (log_scale(count, max) * (shades.len() - 2) as f64).round() as usize;
+                heatmap.push(shades[level + 1]);
+            }
+        }
+        heatmap.push('\n');
+    }
+    heatmap
+}
+
+// One line per row with the exact path count for every column, counting splitters the
+// same way as the other heatmaps
+fn render_heatmap_csv(grid: &[Vec<String>], ways: &[Vec<u128>]) -> String {
+    let mut csv = String::new();
+    for (r, counts) in ways.iter().enumerate() {
+        let cells: Vec<String> = (0..counts.len())
+            .map(|c| paths_through_cell(grid, ways, r, c).to_string())
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// Colour for a log-scaled count: black through red and yellow up to white
+fn heat_colour(t: f64) -> [u8; 3] {
+    let channel = |from: f64| ((t * 3.0 - from).clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(0.0), channel(1.0), channel(2.0)]
+}
+
+// A binary PPM (P6) image of the heatmap with each cell drawn as a square of
+// `pixel_size` pixels. Cells with no paths are black and splitters nobody reaches are grey.
+fn render_heatmap_ppm(grid: &[Vec<String>], ways: &[Vec<u128>], pixel_size: usize) -> Vec<u8> {
+    let max = ways.iter().flatten().copied().max().unwrap_or(0);
+    let width = ways.first().map_or(0, |row| row.len());
+    let mut image = format!("P6\n{} {}\n255\n", width * pixel_size, ways.len() * pixel_size).into_bytes();
+    for (r, row) in grid.iter().enumerate() {
+        // Every row is as wide as the header says, whatever the length of its line
+        let colours: Vec<[u8; 3]> = (0..width)
+            .map(|c| match paths_through_cell(grid, ways, r, c) {
+                0 if row.get(c).is_some_and(|cell| cell == "^") => [96, 96, 96],
+                0 => [0, 0, 0],
+                count => heat_colour(log_scale(count, max)),
+            })
+            .collect();
+        for _ in 0..pixel_size {
+            for colour in &colours {
+                for _ in 0..pixel_size {
+                    image.extend_from_slice(colour);
+                }
+            }
+        }
+    }
+    image
+}
+
+// Build a grid and make paths from the S counter spliting the beam when 
+// encountering ^ characters. Count the number of unique paths that are created
+// by the splitting of the beams. Where a path is started at S and ends 
+// when the path gets to the edge of the grid.
+// The heatmap options show how many paths pass through each cell on the way.
+fn solutioner_for_part_2(input: &str, args: &Args) -> io::Result<String> {
+    let grid = convert_string_into_two_d_vector(input);
+
+    let Some(ways) = count_timelines_per_row(&grid) else {
+        return Ok("No starting position found".to_string());
+    };
+
+    if args.heatmap {
+        print!("{}", render_heatmap_ascii(&grid, &ways));
+    }
+    if let Some(path) = &args.heatmap_csv {
+        std::fs::write(path, render_heatmap_csv(&grid, &ways))?;
+    }
+    if let Some(path) = &args.heatmap_ppm {
+        std::fs::write(path, render_heatmap_ppm(&grid, &ways, args.pixel_size))?;
+    }
+
+    let last_row = ways.last().unwrap();
+    Ok(last_row.iter().sum::<u128>().to_string())
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// A probability kept as an exact fraction in lowest terms. Arithmetic is checked and
+// gives None once the numbers no longer fit in a u128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rational {
+    numerator: u128,
+    denominator: u128,
+}
+
+impl Rational {
+    fn new(numerator: u128, denominator: u128) -> Option<Rational> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator).max(1);
+        Some(Rational { numerator: numerator / divisor, denominator: denominator / divisor })
+    }
+
+    // Accepts a fraction like 1/3 or a decimal like 0.25, both read exactly
+    fn parse(text: &str) -> Option<Rational> {
+        let text = text.trim();
+        if let Some((numerator, denominator)) = text.split_once('/') {
+            return Rational::new(numerator.trim().parse().ok()?, denominator.trim().parse().ok()?);
+        }
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if (whole.is_empty() && fraction.is_empty()) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+        let denominator = 10u128.checked_pow(fraction.len() as u32)?;
+        let fraction: u128 = if fraction.is_empty() { 0 } else { fraction.parse().ok()? };
+        Rational::new(whole.checked_mul(denominator)?.checked_add(fraction)?, denominator)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+// What the probability DP needs from a number type, so it can run on exact fractions
+// or plain floats. Operations give None when the result can't be represented.
+trait Probability: Copy + PartialEq + std::fmt::Display {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn from_rational(value: Rational) -> Self;
+    fn add(self, other: Self) -> Option<Self>;
+    fn multiply(self, other: Self) -> Option<Self>;
+    fn complement(self) -> Option<Self>;
 }
-     
+
+impl Probability for Rational {
+    fn zero() -> Self {
+        Rational { numerator: 0, denominator: 1 }
+    }
+
+    fn one() -> Self {
+        Rational { numerator: 1, denominator: 1 }
+    }
+
+    fn from_rational(value: Rational) -> Self {
+        value
+    }
+
+    fn add(self, other: Self) -> Option<Self> {
+        // Work over the least common multiple to keep the intermediate numbers small
+        let divisor = gcd(self.denominator, other.denominator);
+        let denominator = (self.denominator / divisor).checked_mul(other.denominator)?;
+        let left = self.numerator.checked_mul(denominator / self.denominator)?;
+        let right = other.numerator.checked_mul(denominator / other.denominator)?;
+        Rational::new(left.checked_add(right)?, denominator)
+    }
+
+    fn multiply(self, other: Self) -> Option<Self> {
+        // Cancel across before multiplying for the same reason
+        let a = gcd(self.numerator, other.denominator).max(1);
+        let b = gcd(other.numerator, self.denominator).max(1);
+        Rational::new(
+            (self.numerator / a).checked_mul(other.numerator / b)?,
+            (self.denominator / b).checked_mul(other.denominator / a)?,
+        )
+    }
+
+    fn complement(self) -> Option<Self> {
+        Rational::new(self.denominator.checked_sub(self.numerator)?, self.denominator)
+    }
+}
+
+impl Probability for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn from_rational(value: Rational) -> Self {
+        value.to_f64()
+    }
+
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn multiply(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn complement(self) -> Option<Self> {
+        Some(1.0 - self)
+    }
+}
+
+// Where the beam ends up when every splitter picks a side at random
+struct ExitDistribution<T> {
+    // Chance of leaving the bottom of the grid from each column
+    columns: Vec<T>,
+    // Chance of being split off the left or right edge of the grid
+    off_left: T,
+    off_right: T,
+}
+
+// Reads the per-splitter probability map, one `row,column,p` line per splitter, where p
+// is the chance of going left. Rows and columns count from 0 like the splitter report.
+fn parse_probability_map(input: &str) -> Result<HashMap<(usize, usize), Rational>, String> {
+    let mut map = HashMap::new();
+    for (line_number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        let entry = match fields.as_slice() {
+            [row, column, p] => row.parse().ok().zip(column.parse().ok()).zip(Rational::parse(p)),
+            _ => None,
+        };
+        match entry {
+            Some((position, p)) if p.numerator <= p.denominator => {
+                map.insert(position, p);
+            }
+            _ => return Err(format!("Line {} of the probability map should be row,column,p with p between 0 and 1: {}", line_number + 1, line)),
+        }
+    }
+    Ok(map)
+}
+
+// The same row by row walk as part 2, but instead of counting timelines each column carries
+// the chance the beam is there. A splitter sends its share left with its probability from
+// the map, or `left` when it isn't in the map, and the rest right.
+fn exit_distribution<T: Probability>(
+    grid: &[Vec<String>],
+    left: Rational,
+    map: &HashMap<(usize, usize), Rational>,
+) -> Result<ExitDistribution<T>, String> {
+    let too_large = || "The exact probabilities got too large, try again without --exact".to_string();
+    let start_x = grid
+        .first()
+        .and_then(|row| row.iter().position(|cell| cell == "S"))
+        .ok_or("No starting position found")?;
+    let width = grid[0].len();
+
+    let mut current = vec![T::zero(); width];
+    current[start_x] = T::one();
+    let mut off_left = T::zero();
+    let mut off_right = T::zero();
+
+    for (r, row) in grid.iter().enumerate().skip(1) {
+        let mut next = vec![T::zero(); width];
+        for (pos, &chance) in current.iter().enumerate() {
+            if chance == T::zero() {
+                continue;
+            }
+            if row[pos] != "^" {
+                next[pos] = next[pos].add(chance).ok_or_else(too_large)?;
+                continue;
+            }
+            let p = T::from_rational(*map.get(&(r, pos)).unwrap_or(&left));
+            let going_left = chance.multiply(p).ok_or_else(too_large)?;
+            let going_right = chance.multiply(p.complement().ok_or_else(too_large)?).ok_or_else(too_large)?;
+            let left_target = if pos > 0 { &mut next[pos - 1] } else { &mut off_left };
+            *left_target = left_target.add(going_left).ok_or_else(too_large)?;
+            let right_target = if pos + 1 < width { &mut next[pos + 1] } else { &mut off_right };
+            *right_target = right_target.add(going_right).ok_or_else(too_large)?;
+        }
+        current = next;
+    }
+
+    Ok(ExitDistribution { columns: current, off_left, off_right })
+}
+
+// One line per place the beam can leave the grid with the chance it leaves there,
+// as a fraction and a decimal for the exact mode or just a decimal otherwise
+fn render_distribution<T: Probability>(distribution: &ExitDistribution<T>, to_f64: impl Fn(T) -> f64, exact: bool) -> String {
+    let line = |label: String, chance: T| {
+        if exact {
+            format!("{}: {} ({})\n", label, chance, to_f64(chance))
+        } else {
+            format!("{}: {}\n", label, chance)
+        }
+    };
+    let mut rendered = String::new();
+    if distribution.off_left != T::zero() {
+        rendered.push_str(&line("off the left edge".to_string(), distribution.off_left));
+    }
+    for (column, &chance) in distribution.columns.iter().enumerate() {
+        if chance != T::zero() {
+            rendered.push_str(&line(format!("column {}", column), chance));
+        }
+    }
+    if distribution.off_right != T::zero() {
+        rendered.push_str(&line("off the right edge".to_string(), distribution.off_right));
+    }
+    rendered
+}
+
+// Instead of splitting every beam both ways, each splitter sends the beam left with
+// probability p and right otherwise. Works out where the beam leaves the grid.
+fn solutioner_for_probabilities(input: &str, args: &Args) -> io::Result<String> {
+    let grid = convert_string_into_two_d_vector(input);
+    let p = args.probability.as_deref().unwrap_or("1/2");
+    let left = match Rational::parse(p) {
+        Some(left) if left.numerator <= left.denominator => left,
+        _ => return Ok(format!("Probability {} should be a fraction or decimal between 0 and 1", p)),
+    };
+    let map = match &args.probability_map {
+        Some(path) => match parse_probability_map(&std::fs::read_to_string(path)?) {
+            Ok(map) => map,
+            Err(message) => return Ok(message),
+        },
+        None => HashMap::new(),
+    };
+
+    let rendered = if args.exact {
+        exit_distribution::<Rational>(&grid, left, &map).map(|distribution| render_distribution(&distribution, Rational::to_f64, true))
+    } else {
+        exit_distribution::<f64>(&grid, left, &map).map(|distribution| render_distribution(&distribution, |chance| chance, false))
+    };
+    Ok(rendered.unwrap_or_else(|message| message))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    fn step(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+
+    fn is_vertical(self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+
+    // The two sides of a beam travelling this way, as steps from where it is
+    fn sides(self) -> [(isize, isize); 2] {
+        if self.is_vertical() { [(0, -1), (0, 1)] } else { [(-1, 0), (1, 0)] }
+    }
+}
+
+// What a cell does to a beam travelling through it: each resulting beam as the step from
+// the cell to where it starts and the direction it then travels in.
+//   . and S   let the beam carry on
+//   ^         splits the beam, which carries on in the same direction from both cells beside
+//             the splitter (left and right of a downward beam)
+//   / and \   mirrors that turn the beam 90 degrees
+//   |         splits a sideways beam into one going up and one going down
+//   -         splits an up or down beam into one going left and one going right
+//   #         absorbs the beam
+fn beams_leaving_cell(cell: &str, direction: Direction) -> Vec<((isize, isize), Direction)> {
+    use Direction::*;
+    match (cell, direction) {
+        ("^", _) => direction.sides().iter().map(|&side| (side, direction)).collect(),
+        ("/", Up) => vec![((0, 0), Right)],
+        ("/", Right) => vec![((0, 0), Up)],
+        ("/", Down) => vec![((0, 0), Left)],
+        ("/", Left) => vec![((0, 0), Down)],
+        ("\\", Up) => vec![((0, 0), Left)],
+        ("\\", Left) => vec![((0, 0), Up)],
+        ("\\", Down) => vec![((0, 0), Right)],
+        ("\\", Right) => vec![((0, 0), Down)],
+        ("|", Left | Right) => vec![((0, 0), Up), ((0, 0), Down)],
+        ("-", Up | Down) => vec![((0, 0), Left), ((0, 0), Right)],
+        ("#", _) => vec![],
+        _ => vec![((0, 0), direction)],
+    }
+}
+
+// Where a beam goes next after passing through a cell. `from` is the cell it sets off from,
+// which is the cell itself except for the cells beside a ^ splitter.
+enum Next {
+    // Into another cell of the grid
+    Cell { from: usize, state: usize },
+    // Out of the grid, leaving from this cell in this direction
+    Exit { from: usize, direction: Direction },
+}
+
+// What happened to every beam that started from S heading down
+struct Simulation {
+    splits: usize,
+    energized: usize,
+    exits: usize,
+    loops: usize,
+    // Distinct paths from S out of the grid, or None when a loop makes them endless
+    timelines: Option<u128>,
+}
+
+// Follow the beam from S through the grid. Each state is a cell plus the direction the beam
+// is travelling as it enters; a depth-first walk over the states visits each one once, counts
+// the paths out of the grid from each state on the way back, and spots loops as states that
+// lead back to one still being explored. Beams split off the edge of the grid by ^ are lost,
+// as in parts 1 and 2, so a grid of only ^ splitters gives the part 1 count as `splits`
+// and the part 2 count as `timelines`.
+fn simulate_beams(grid: &[Vec<String>]) -> Option<Simulation> {
+    let start_x = grid.first()?.iter().position(|cell| cell == "S")?;
+    let height = grid.len();
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    let cell_at = |r: usize, c: usize| grid[r].get(c).map_or(".", |cell| cell.as_str());
+    let state_of = |r: usize, c: usize, d: Direction| (r * width + c) * 4 + d as usize;
+    let in_grid = |r: isize, c: isize| r >= 0 && r < height as isize && c >= 0 && c < width as isize;
+
+    let next_from = |state: usize| -> (Vec<Next>, bool) {
+        let (r, c, d) = (state / 4 / width, state / 4 % width, Direction::ALL[state % 4]);
+        let leaving = beams_leaving_cell(cell_at(r, c), d);
+        let split = leaving.len() > 1;
+        let mut next = Vec::new();
+        for ((dr, dc), direction) in leaving {
+            let (origin_r, origin_c) = (r as isize + dr, c as isize + dc);
+            if !in_grid(origin_r, origin_c) {
+                continue;
+            }
+            let from = origin_r as usize * width + origin_c as usize;
+            let (sr, sc) = direction.step();
+            let (nr, nc) = (origin_r + sr, origin_c + sc);
+            if in_grid(nr, nc) {
+                let state = state_of(nr as usize, nc as usize, direction);
+                next.push(Next::Cell { from, state });
+            } else {
+                next.push(Next::Exit { from, direction });
+            }
+        }
+        (next, split)
+    };
+
+    // 0 = not seen, 1 = being explored, 2 = done
+    let mut status = vec![0u8; height * width * 4];
+    let mut paths: Vec<Option<u128>> = vec![Some(0); height * width * 4];
+    let mut energized = vec![false; height * width];
+    let mut exits: Vec<(usize, Direction)> = Vec::new();
+    let mut splits = 0;
+    let mut loops = 0;
+
+    let start = state_of(0, start_x, Direction::Down);
+    let mut stack = vec![(start, false)];
+    while let Some((state, children_done)) = stack.pop() {
+        let (next, split) = next_from(state);
+        if children_done {
+            let mut total = Some(0u128);
+            for n in &next {
+                let count = match n {
+                    Next::Cell { state, .. } => paths[*state],
+                    Next::Exit { .. } => Some(1),
+                };
+                total = total.zip(count).and_then(|(a, b)| a.checked_add(b));
+            }
+            paths[state] = total;
+            status[state] = 2;
+            continue;
+        }
+        if status[state] != 0 {
+            continue;
+        }
+        status[state] = 1;
+        energized[state / 4] = true;
+        if split {
+            splits += 1;
+        }
+        stack.push((state, true));
+        for n in next {
+            match n {
+                Next::Cell { from, state: child } => {
+                    energized[from] = true;
+                    if status[child] == 1 {
+                        // Back to a state still being explored, so this beam goes round forever
+                        loops += 1;
+                    } else {
+                        stack.push((child, false));
+                    }
+                }
+                Next::Exit { from, direction } => {
+                    energized[from] = true;
+                    if !exits.contains(&(from, direction)) {
+                        exits.push((from, direction));
+                    }
+                }
+            }
+        }
+    }
+
+    Some(Simulation {
+        splits,
+        energized: energized.iter().filter(|&&lit| lit).count(),
+        exits: exits.len(),
+        loops,
+        timelines: if loops > 0 { None } else { paths[start] },
+    })
+}
+
+// Run the general beam simulation and report everything it found
+fn solutioner_for_simulation(input: &str) -> String {
+    let grid = convert_string_into_two_d_vector(input);
+    let Some(simulation) = simulate_beams(&grid) else {
+        return "No starting position found".to_string();
+    };
+    let timelines = match simulation.timelines {
+        Some(count) => count.to_string(),
+        None => "endless, the beams loop".to_string(),
+    };
+    [
+        format!("Splits: {}", simulation.splits),
+        format!("Energized cells: {}", simulation.energized),
+        format!("Exit points: {}", simulation.exits),
+        format!("Loops: {}", simulation.loops),
+        format!("Timelines: {}", timelines),
+    ]
+    .join("\n")
+}
+
 /// Main function to read input file, process it, and write to output file
 /// Uses command line arguments for input and which part you want to solve 
 /// Example usage: cargo run -- --i input.txt --p 1
@@ -134,8 +845,17 @@ fn main() -> io::Result<()> {
     input_file.read_to_string(&mut contents)?;
 
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+        _ if args.simulate => solutioner_for_simulation(&contents),
+        _ if args.probability.is_some() || args.probability_map.is_some() || args.exact => {
+            solutioner_for_probabilities(&contents, &args)?
+        }
+        1 => {
+            if args.splitters {
+                print!("{}", splitter_report(&contents));
+            }
+            solutioner_for_part_1(&contents)
+        }
+        2 => solutioner_for_part_2(&contents, &args)?,
         _ => "Invalid part specified".to_string(),
     };
 
@@ -1,7 +1,9 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::fs::File;
 use std::io::{self, Read, Write};
-use std::collections::{HashMap, BinaryHeap};
+use std::collections::{BTreeMap, BinaryHeap};
+use std::cmp::Reverse;
+use kiddo::{Chebyshev, KdTree, Manhattan, SquaredEuclidean};
 
 #[derive(Parser, Debug, Clone, PartialEq)]
 #[command(name = "solutions_runner")]
@@ -14,30 +16,132 @@ struct Args {
     // Part to solve (1 or 2)
     #[arg(short, long, default_value_t = 1)]
     part: i32,
+
+    /// How many of the closest pairs to connect in part 1 (10 for the small input)
+    #[arg(short, long, default_value_t = 1000)]
+    connections: usize,
+
+    /// How many of the largest circuits to multiply together in part 1
+    #[arg(short, long, default_value_t = 3)]
+    top: usize,
+
+    /// Print the top circuit product and number of circuits after every connection up to --connections
+    #[arg(long)]
+    sweep: bool,
+
+    /// Write the part 2 merge history to this file as a Newick tree
+    #[arg(long)]
+    newick: Option<String>,
+
+    /// Write the part 2 merge history with merge distances to this JSON file
+    #[arg(long)]
+    dendrogram_json: Option<String>,
+
+    /// Print the circuits left when only merges up to this distance are made (part 2)
+    #[arg(long)]
+    cut_distance: Option<f64>,
+
+    /// Print the circuits left when merging stops at this many circuits (part 2)
+    #[arg(long)]
+    cut_clusters: Option<usize>,
+
+    /// How to measure the distance between two junction boxes
+    #[arg(short, long, value_enum, default_value_t = MetricKind::Euclidean)]
+    metric: MetricKind,
+
+    /// Integer weights for the x, y and z axes with the weighted metric
+    #[arg(short, long, value_delimiter = ',', default_values_t = [1, 1, 1])]
+    weights: Vec<u32>,
+
+    /// Write the connections made (part 1) or the spanning tree (part 2) as a Graphviz DOT file
+    #[arg(long)]
+    dot: Option<String>,
+
+    /// Write the points, their circuits and the connections made to this JSON file
+    #[arg(long)]
+    graph_json: Option<String>,
 }
-struct OrderedFloat(f64);
 
-impl Eq for OrderedFloat {}
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum MetricKind {
+    /// Straight line distance
+    Euclidean,
+    /// Sum of the distances along each axis
+    Manhattan,
+    /// Largest distance along any one axis
+    Chebyshev,
+    /// Straight line distance with each axis scaled by --weights
+    Weighted,
+}
 
-impl PartialOrd for OrderedFloat {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
-    }
+// A distance between points that can be compared exactly as an integer. The Euclidean
+// metrics compare squared distances so they never need a square root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    // Sum of weight * difference squared for each axis
+    Weighted([i64; 3]),
 }
 
-impl Ord for OrderedFloat {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+impl Metric {
+    fn from_args(kind: MetricKind, weights: &[u32]) -> Result<Metric, String> {
+        Ok(match kind {
+            MetricKind::Euclidean => Metric::Euclidean,
+            MetricKind::Manhattan => Metric::Manhattan,
+            MetricKind::Chebyshev => Metric::Chebyshev,
+            MetricKind::Weighted => match weights {
+                &[x, y, z] => Metric::Weighted([x as i64, y as i64, z as i64]),
+                _ => return Err(format!("Expected 3 weights for x, y and z but got {}", weights.len())),
+            },
+        })
     }
-}
 
-impl PartialEq for OrderedFloat {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+    // Differences of i32 coordinates only fit in an i64, and their squares need an i128
+    fn distance(self, p: (i32, i32, i32), q: (i32, i32, i32)) -> i128 {
+        self.combine([p.0 as i128 - q.0 as i128, p.1 as i128 - q.1 as i128, p.2 as i128 - q.2 as i128])
+    }
+
+    // The distance for the given differences along the x, y and z axes
+    fn combine(self, deltas: [i128; 3]) -> i128 {
+        match self {
+            Metric::Euclidean => deltas.iter().map(|&d| d * d).sum(),
+            Metric::Manhattan => deltas.iter().map(|&d| d.abs()).sum(),
+            Metric::Chebyshev => deltas.iter().map(|&d| d.abs()).max().unwrap(),
+            Metric::Weighted(weights) => deltas.iter().zip(weights).map(|(&d, w)| d * d * w as i128).sum(),
+        }
+    }
+
+    // The distance in the same units as the coordinates, for reports and cut thresholds
+    fn length(self, distance: i128) -> f64 {
+        match self {
+            Metric::Euclidean | Metric::Weighted(_) => (distance as f64).sqrt(),
+            Metric::Manhattan | Metric::Chebyshev => distance as f64,
+        }
     }
-}
 
+    // Where the point goes in the kd-tree. Weighted axes are stretched by the square root of
+    // their weight so plain squared Euclidean distance in the tree matches the metric.
+    fn tree_point(self, p: (i32, i32, i32)) -> [f64; 3] {
+        let scale = match self {
+            Metric::Weighted(weights) => weights.map(|w| (w as f64).sqrt()),
+            _ => [1.0; 3],
+        };
+        [p.0 as f64 * scale[0], p.1 as f64 * scale[1], p.2 as f64 * scale[2]]
+    }
 
+    // The `k` points in the tree closest to `p` with their distance as the tree measured it
+    fn nearest(self, tree: &KdTree<f64, 3>, p: (i32, i32, i32), k: usize) -> Vec<(f64, usize)> {
+        let query = self.tree_point(p);
+        let found = match self {
+            Metric::Euclidean | Metric::Weighted(_) => tree.nearest_n::<SquaredEuclidean>(&query, k),
+            Metric::Manhattan => tree.nearest_n::<Manhattan>(&query, k),
+            Metric::Chebyshev => tree.nearest_n::<Chebyshev>(&query, k),
+        };
+        found.into_iter().map(|neighbour| (neighbour.distance, neighbour.item as usize)).collect()
+    }
+}
 // Each line contains one point in the format "x,y,z"
 fn parse_list_of_points(input: &str) -> Vec<(i32, i32, i32)> {
     input
@@ -52,154 +156,620 @@ fn parse_list_of_points(input: &str) -> Vec<(i32, i32, i32)> {
         .collect()
 }
 
-// Given a list of 3D points, find the sizes of connected components
-// where edges are formed between points that are among the `num_edges` shortest distances
-// Return the product of the sizes of the three largest components
-fn solutioner_for_part_1(input: &str) -> String {
-    let num_edges: usize = 1000; // Just adjusted this manually from small input, 10 to larger input, 1000 
-    let list_of_points = parse_list_of_points(input);
-    let n = list_of_points.len();
+// How many neighbours each point asks the kd-tree for the first time
+const INITIAL_NEIGHBOURS: usize = 8;
+
+// Relative error allowed for in the kd-tree's f64 distances. The rounding of a few f64
+// operations is around 1e-15, so this leaves plenty of room.
+const TREE_DISTANCE_MARGIN: f64 = 1e-9;
+
+// The neighbours of one point, nearest first. Whenever the ones fetched so far run out the
+// kd-tree is asked again for twice as many, so each point only looks as far out as the
+// closest-first walk actually needs.
+struct NeighbourStream {
+    point: usize,
+    // Neighbours fetched but not handed out yet, nearest first and then by index
+    fetched: Vec<(i128, usize)>,
+    next: usize,
+    // How many neighbours to ask for on the next query
+    k: usize,
+    // Every neighbour closer than this has already been fetched
+    bound: i128,
+    exhausted: bool,
+}
 
-    // Use a max-heap to track the global `num_edges` shortest edges (undirected)
-    let mut min_heap = BinaryHeap::new();
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let p = list_of_points[i];
-            let q = list_of_points[j];
-            let dx = (p.0 - q.0) as f64;
-            let dy = (p.1 - q.1) as f64;
-            let dz = (p.2 - q.2) as f64;
-            let dist_sq = dx * dx + dy * dy + dz * dz;
+impl NeighbourStream {
+    fn new(point: usize) -> Self {
+        NeighbourStream { point, fetched: Vec::new(), next: 0, k: INITIAL_NEIGHBOURS, bound: 0, exhausted: false }
+    }
 
-            min_heap.push((OrderedFloat(dist_sq), i, j));
-            if min_heap.len() > num_edges {
-                // Remove the longest edge seen so far since we only want the shortest `num_edges` edges
-                min_heap.pop();
+    fn next(&mut self, points: &[(i32, i32, i32)], tree: &KdTree<f64, 3>, metric: Metric) -> Option<(i128, usize)> {
+        while self.next == self.fetched.len() {
+            if self.exhausted {
+                return None;
             }
+            self.refill(points, tree, metric);
         }
+        self.next += 1;
+        Some(self.fetched[self.next - 1])
     }
 
-    // Extract edges
-    let mut edges: Vec<_> = min_heap.into_iter()
-        .map(|(OrderedFloat(d), i, j)| (d, i, j))
-        .collect();
-    edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    fn refill(&mut self, points: &[(i32, i32, i32)], tree: &KdTree<f64, 3>, metric: Metric) {
+        let p = points[self.point];
+        let k = self.k.min(points.len());
+        // The kd-tree only picks the candidates, the order comes from the exact distances
+        let nearest = metric.nearest(tree, p, k);
+        let farthest = nearest.iter().map(|&(d, _)| d).fold(0.0, f64::max);
+        let mut found: Vec<(i128, usize)> = nearest
+            .into_iter()
+            .filter(|&(_, j)| j != self.point)
+            .map(|(_, j)| (metric.distance(p, points[j]), j))
+            .collect();
+        found.sort_unstable();
+
+        // Every point the tree left out is at least as far as the farthest one it found, but
+        // only as the tree measures it. Past 2^53 its f64 distances round, so only trust points
+        // that are exactly closer than that distance less a margin well above the rounding
+        // error, unless every point came back.
+        let cut = if k == points.len() {
+            self.exhausted = true;
+            i128::MAX
+        } else {
+            (farthest * (1.0 - TREE_DISTANCE_MARGIN)) as i128
+        };
+        self.fetched = found.into_iter().filter(|&(d, _)| d >= self.bound && d < cut).collect();
+        self.next = 0;
+        self.bound = cut;
+        self.k *= 2;
+    }
+}
 
-    // Union-Find structure to find connected components
-    let mut parent: Vec<usize> = (0..n).collect();
-    let mut rank: Vec<usize> = vec![0; n];
+// Every pair of points exactly once as (distance, i, j) with i < j, shortest first under
+// the metric. Pairs at the same distance come out ordered by i and then j, the order the
+// points appear in the input, so which of them gets connected first never depends on the
+// heap. This merges the neighbour streams of all points through a heap instead of listing
+// all n(n-1)/2 pairs up front.
+struct ClosestPairs<'a> {
+    points: &'a [(i32, i32, i32)],
+    tree: KdTree<f64, 3>,
+    metric: Metric,
+    streams: Vec<NeighbourStream>,
+    heap: BinaryHeap<Reverse<(i128, usize, usize)>>,
+}
 
-    fn find(x: usize, parent: &mut [usize]) -> usize {
-        if parent[x] != x {
-            parent[x] = find(parent[x], parent);
+impl<'a> ClosestPairs<'a> {
+    fn new(points: &'a [(i32, i32, i32)], metric: Metric) -> Self {
+        let coordinates: Vec<[f64; 3]> = points.iter().map(|&p| metric.tree_point(p)).collect();
+        let tree: KdTree<f64, 3> = (&coordinates).into();
+        let mut streams: Vec<NeighbourStream> = (0..points.len()).map(NeighbourStream::new).collect();
+        let mut heap = BinaryHeap::new();
+        for stream in &mut streams {
+            if let Some((d, j)) = stream.next(points, &tree, metric) {
+                heap.push(Reverse((d, stream.point, j)));
+            }
         }
-        parent[x]
+        ClosestPairs { points, tree, metric, streams, heap }
     }
+}
 
-    fn union(x: usize, y: usize, parent: &mut [usize], rank: &mut [usize]) {
-        let rx = find(x, parent);
-        let ry = find(y, parent);
-        if rx != ry {
-            if rank[rx] < rank[ry] {
-                parent[rx] = ry;
-            } else if rank[rx] > rank[ry] {
-                parent[ry] = rx;
-            } else {
-                parent[ry] = rx;
-                rank[rx] += 1;
+impl Iterator for ClosestPairs<'_> {
+    type Item = (i128, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Reverse((d, i, j)) = self.heap.pop()?;
+            if let Some((next_d, next_j)) = self.streams[i].next(self.points, &self.tree, self.metric) {
+                self.heap.push(Reverse((next_d, i, next_j)));
+            }
+            // Each pair shows up in the streams of both its points, keep the one from the lower index
+            if i < j {
+                return Some((d, i, j));
             }
         }
     }
+}
+
+// Union-Find over the junction boxes that also keeps the size of every circuit, how many
+// circuits there are and how many circuits have each size, so the largest circuits can
+// be read off after any connection without recounting
+struct Circuits {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+    // Circuit size -> number of circuits with that size
+    size_counts: BTreeMap<usize, usize>,
+}
 
-    for (_, i, j) in &edges {
-        union(*i, *j, &mut parent, &mut rank);
+impl Circuits {
+    fn new(n: usize) -> Self {
+        let mut size_counts = BTreeMap::new();
+        if n > 0 {
+            size_counts.insert(1, n);
+        }
+        Circuits { parent: (0..n).collect(), rank: vec![0; n], size: vec![1; n], count: n, size_counts }
     }
 
-    // Count circuit sizes
-    let mut sizes: HashMap<usize, usize> = HashMap::new();
-    for idx in 0..n {
-        *sizes.entry(find(idx, &mut parent)).or_insert(0) += 1;
+    fn find(&mut self, x: usize) -> usize {
+        if self.parent[x] != x {
+            self.parent[x] = self.find(self.parent[x]);
+        }
+        self.parent[x]
     }
 
-    let mut size_vec: Vec<_> = sizes.values().copied().collect();
-    size_vec.sort_unstable_by(|a, b| b.cmp(a));
+    fn remove_size(&mut self, size: usize) {
+        if let Some(count) = self.size_counts.get_mut(&size) {
+            *count -= 1;
+            if *count == 0 {
+                self.size_counts.remove(&size);
+            }
+        }
+    }
 
-    size_vec.iter().take(3).product::<usize>().to_string()
+    // Connect the circuits of x and y, returns false if they were already the same circuit
+    fn union(&mut self, x: usize, y: usize) -> bool {
+        let rx = self.find(x);
+        let ry = self.find(y);
+        if rx == ry {
+            return false;
+        }
+        let (root, child) = if self.rank[rx] < self.rank[ry] { (ry, rx) } else { (rx, ry) };
+        if self.rank[rx] == self.rank[ry] {
+            self.rank[root] += 1;
+        }
+        self.parent[child] = root;
+
+        self.remove_size(self.size[root]);
+        self.remove_size(self.size[child]);
+        self.size[root] += self.size[child];
+        *self.size_counts.entry(self.size[root]).or_insert(0) += 1;
+        self.count -= 1;
+        true
+    }
+
+    // The points in each circuit, largest circuit first and then by lowest point
+    fn members(&mut self) -> Vec<Vec<usize>> {
+        let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for point in 0..self.parent.len() {
+            members.entry(self.find(point)).or_default().push(point);
+        }
+        let mut clusters: Vec<Vec<usize>> = members.into_values().collect();
+        clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        clusters
+    }
+
+    // Product of the sizes of the `top` largest circuits
+    fn top_product(&self, top: usize) -> usize {
+        self.size_counts
+            .iter()
+            .rev()
+            .flat_map(|(&size, &count)| std::iter::repeat_n(size, count))
+            .take(top)
+            .product()
+    }
 }
 
+// Given a list of 3D points, find the sizes of connected components
+// where edges are formed between points that are among the `connections` shortest distances
+// Return the product of the sizes of the `top` largest components
+fn solutioner_for_part_1(input: &str, args: &Args, metric: Metric) -> io::Result<String> {
+    let list_of_points = parse_list_of_points(input);
+    let mut circuits = Circuits::new(list_of_points.len());
 
-// Connect all points using closest-first spanning (Kruskal) and
-// return the product of the X coordinates of the endpoints of the final merge.
-fn solutioner_for_part_2(input: &str) -> String {
+    let edges: Vec<_> = ClosestPairs::new(&list_of_points, metric).take(args.connections).collect();
+    for &(_, i, j) in &edges {
+        circuits.union(i, j);
+    }
+
+    export_graph(args, metric, &list_of_points, &edges, &circuits.members())?;
+    Ok(circuits.top_product(args.top).to_string())
+}
+
+// Part 1 after every connection from 1 up to `connections`, all from one pass over the pairs
+fn solutioner_for_sweep(input: &str, args: &Args, metric: Metric) -> String {
     let list_of_points = parse_list_of_points(input);
-    let n = list_of_points.len();
+    let mut circuits = Circuits::new(list_of_points.len());
+
+    let mut sweep = String::from("connections,top_product,circuits\n");
+    for (connection, (_, i, j)) in ClosestPairs::new(&list_of_points, metric).take(args.connections).enumerate() {
+        circuits.union(i, j);
+        sweep.push_str(&format!("{},{},{}\n", connection + 1, circuits.top_product(args.top), circuits.count));
+    }
+    sweep
+}
+
 
-    // Build all undirected edges with squared distances
-    let mut edges: Vec<_> = Vec::new();
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let p = list_of_points[i];
-            let q = list_of_points[j];
-            let dx = (p.0 - q.0) as f64;
-            let dy = (p.1 - q.1) as f64;
-            let dz = (p.2 - q.2) as f64;
-            let dist_sq = dx * dx + dy * dy + dz * dz;
+// One step of single-linkage clustering. Points are nodes 0..n and the k-th merge
+// creates node n + k out of the two nodes whose circuits it joins.
+struct Merge {
+    // The pair of points whose connection caused the merge
+    i: usize,
+    j: usize,
+    // Exact distance under the metric and the same in coordinate units
+    distance: i128,
+    length: f64,
+    left: usize,
+    right: usize,
+    size: usize,
+}
+
+// Points per leaf of the circuit tree
+const LEAF_SIZE: usize = 16;
+
+struct TreeNode {
+    // The node covers order[start..end]
+    start: usize,
+    end: usize,
+    // Bounding box of those points
+    low: [i32; 3],
+    high: [i32; 3],
+    children: Option<(usize, usize)>,
+    // The circuit every point under this node is in, if they are all in the same one
+    circuit: Option<usize>,
+}
+
+// A kd-tree for finding the closest point in a different circuit. Unlike the kiddo tree it
+// knows which circuit its points are in, so a search can skip any subtree that lies wholly
+// inside its own circuit. Bounds are worked out exactly with the metric, no floats involved.
+struct CircuitTree {
+    order: Vec<usize>,
+    nodes: Vec<TreeNode>,
+}
 
-            edges.push((OrderedFloat(dist_sq), i, j));
+impl CircuitTree {
+    fn new(points: &[(i32, i32, i32)]) -> Self {
+        let mut tree = CircuitTree { order: (0..points.len()).collect(), nodes: Vec::new() };
+        if !points.is_empty() {
+            tree.build(points, 0, points.len());
         }
+        tree
     }
 
-    // Sort ascending by distance for Kruskal
-    edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    fn coordinates(p: (i32, i32, i32)) -> [i32; 3] {
+        [p.0, p.1, p.2]
+    }
 
-    // Union-Find structure to connect points 
-    let mut parent: Vec<usize> = (0..n).collect();
-    let mut rank: Vec<usize> = vec![0; n];
+    // Add the node for order[start..end] and everything under it, splitting on the
+    // widest axis at the median. Children always come after their parent.
+    fn build(&mut self, points: &[(i32, i32, i32)], start: usize, end: usize) -> usize {
+        let mut low = [i32::MAX; 3];
+        let mut high = [i32::MIN; 3];
+        for &point in &self.order[start..end] {
+            let c = CircuitTree::coordinates(points[point]);
+            for axis in 0..3 {
+                low[axis] = low[axis].min(c[axis]);
+                high[axis] = high[axis].max(c[axis]);
+            }
+        }
+        let idx = self.nodes.len();
+        self.nodes.push(TreeNode { start, end, low, high, children: None, circuit: None });
+
+        if end - start > LEAF_SIZE {
+            let axis = (0..3).max_by_key(|&axis| high[axis] as i64 - low[axis] as i64).unwrap();
+            let middle = (start + end) / 2;
+            self.order[start..end]
+                .select_nth_unstable_by_key(middle - start, |&point| CircuitTree::coordinates(points[point])[axis]);
+            let left = self.build(points, start, middle);
+            let right = self.build(points, middle, end);
+            self.nodes[idx].children = Some((left, right));
+        }
+        idx
+    }
 
-    fn find(x: usize, parent: &mut [usize]) -> usize {
-        if parent[x] != x {
-            parent[x] = find(parent[x], parent);
+    // Record which nodes lie wholly inside one circuit, given the circuit of every point
+    fn label(&mut self, circuit_of: &[usize]) {
+        for idx in (0..self.nodes.len()).rev() {
+            let circuit = match self.nodes[idx].children {
+                Some((left, right)) => match (self.nodes[left].circuit, self.nodes[right].circuit) {
+                    (Some(a), Some(b)) if a == b => Some(a),
+                    _ => None,
+                },
+                None => {
+                    let node = &self.nodes[idx];
+                    let first = circuit_of[self.order[node.start]];
+                    self.order[node.start..node.end]
+                        .iter()
+                        .all(|&point| circuit_of[point] == first)
+                        .then_some(first)
+                }
+            };
+            self.nodes[idx].circuit = circuit;
         }
-        parent[x]
     }
 
-    fn union(x: usize, y: usize, parent: &mut [usize], rank: &mut [usize]) -> bool {
-        let rx = find(x, parent);
-        let ry = find(y, parent);
-        if rx != ry {
-            if rank[rx] < rank[ry] {
-                parent[rx] = ry;
-            } else if rank[rx] > rank[ry] {
-                parent[ry] = rx;
-            } else {
-                parent[ry] = rx;
-                rank[rx] += 1;
+    // The smallest distance from p to anything inside the node's bounding box
+    fn lower_bound(&self, idx: usize, p: [i32; 3], metric: Metric) -> i128 {
+        let node = &self.nodes[idx];
+        let gaps = [0, 1, 2].map(|axis| {
+            (node.low[axis] as i128 - p[axis] as i128).max(p[axis] as i128 - node.high[axis] as i128).max(0)
+        });
+        metric.combine(gaps)
+    }
+
+    // Improve `best` with any pair between `point` and a point outside its circuit that
+    // comes before it in (distance, lower index, higher index) order
+    fn closest_outside(
+        &self,
+        points: &[(i32, i32, i32)],
+        metric: Metric,
+        circuit_of: &[usize],
+        point: usize,
+        best: &mut (i128, usize, usize),
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let p = CircuitTree::coordinates(points[point]);
+        let circuit = circuit_of[point];
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            // Pairs at exactly the best distance might still win on index, so only skip farther ones
+            if node.circuit == Some(circuit) || self.lower_bound(idx, p, metric) > best.0 {
+                continue;
+            }
+            match node.children {
+                Some((left, right)) => {
+                    // Look at the nearer child first so the bound tightens sooner
+                    if self.lower_bound(left, p, metric) <= self.lower_bound(right, p, metric) {
+                        stack.extend([right, left]);
+                    } else {
+                        stack.extend([left, right]);
+                    }
+                }
+                None => {
+                    for &other in &self.order[node.start..node.end] {
+                        if circuit_of[other] != circuit {
+                            let pair = (metric.distance(points[point], points[other]), point.min(other), point.max(other));
+                            if pair < *best {
+                                *best = pair;
+                            }
+                        }
+                    }
+                }
             }
-            return true;
         }
-        false
     }
+}
 
-    // Process edges in order, track the last successful connection 
-    let mut merges = 0;
-    let mut last_merge: Option<(usize, usize)> = None;
-    for (_, i, j) in &edges {
-        if union(*i, *j, &mut parent, &mut rank) {
-            merges += 1;
-            last_merge = Some((*i, *j));
-            if merges == n - 1 {
-                break;
+// The minimum spanning tree as (distance, i, j) pairs, found with Borůvka's algorithm: every
+// round each circuit is joined by the closest pair leaving it. With ties settled by index
+// there is only one minimum spanning tree and those pairs are always in it. Going through
+// every pair in order instead would reach out as far as the longest tree edge from every
+// point, so one far-away point would have every point search the whole cloud.
+fn spanning_tree(points: &[(i32, i32, i32)], metric: Metric) -> Vec<(i128, usize, usize)> {
+    let n = points.len();
+    let mut tree = CircuitTree::new(points);
+    let mut circuits = Circuits::new(n);
+    let mut edges = Vec::new();
+
+    while circuits.count > 1 {
+        let circuit_of: Vec<usize> = (0..n).map(|point| circuits.find(point)).collect();
+        tree.label(&circuit_of);
+
+        // Closest pair leaving each circuit, kept under the circuit's root
+        let mut best = vec![(i128::MAX, usize::MAX, usize::MAX); n];
+        for point in 0..n {
+            tree.closest_outside(points, metric, &circuit_of, point, &mut best[circuit_of[point]]);
+        }
+        for root in 0..n {
+            let (distance, i, j) = best[root];
+            // Two circuits can pick the same pair, only the first joins them
+            if circuit_of[root] == root && circuits.union(i, j) {
+                edges.push((distance, i, j));
             }
         }
     }
+    edges
+}
+
+// Connect all points using closest-first spanning (Kruskal), keeping every merge. Only the
+// spanning tree pairs ever join two circuits, so replaying those shortest first gives the
+// same merges as going through every pair.
+fn single_linkage(points: &[(i32, i32, i32)], metric: Metric) -> Vec<Merge> {
+    let n = points.len();
+    let mut edges = spanning_tree(points, metric);
+    edges.sort_unstable();
+
+    let mut circuits = Circuits::new(n);
+    // Dendrogram node currently standing for the circuit with this root
+    let mut node_of_root: Vec<usize> = (0..n).collect();
+    let mut merges: Vec<Merge> = Vec::new();
+
+    for (distance, i, j) in edges {
+        let (left, right) = (node_of_root[circuits.find(i)], node_of_root[circuits.find(j)]);
+        circuits.union(i, j);
+        let root = circuits.find(i);
+        node_of_root[root] = n + merges.len();
+        merges.push(Merge { i, j, distance, length: metric.length(distance), left, right, size: circuits.size[root] });
+    }
+    merges
+}
+
+// The dendrogram in Newick format with points labelled by their line index from 0 and
+// branch lengths being the distance between merge heights. Written with an explicit stack
+// since the tree can be as deep as there are points.
+fn dendrogram_to_newick(merges: &[Merge], n: usize) -> String {
+    enum Step {
+        // Write a node, with the height of its parent to work out its branch length
+        Node(usize, f64),
+        Comma,
+        // Close the brackets of an internal node once both children are written
+        Close(usize, f64),
+    }
+
+    if n == 0 {
+        return ";\n".to_string();
+    }
+    let height = |node: usize| if node < n { 0.0 } else { merges[node - n].length };
+    let root = n + merges.len() - 1;
+    let branch = |node: usize, parent_height: f64| {
+        if node == root { String::new() } else { format!(":{}", parent_height - height(node)) }
+    };
+
+    let mut newick = String::new();
+    let mut stack = vec![Step::Node(root, height(root))];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Node(node, parent_height) if node < n => {
+                newick.push_str(&node.to_string());
+                newick.push_str(&branch(node, parent_height));
+            }
+            Step::Node(node, parent_height) => {
+                let merge = &merges[node - n];
+                newick.push('(');
+                stack.push(Step::Close(node, parent_height));
+                stack.push(Step::Node(merge.right, merge.length));
+                stack.push(Step::Comma);
+                stack.push(Step::Node(merge.left, merge.length));
+            }
+            Step::Comma => newick.push(','),
+            Step::Close(node, parent_height) => {
+                newick.push(')');
+                newick.push_str(&branch(node, parent_height));
+            }
+        }
+    }
+    newick.push_str(";\n");
+    newick
+}
+
+// The points and every merge with its distance, both exact under the metric (squared for the
+// Euclidean ones) and in coordinate units, for loading into other tools
+fn dendrogram_to_json(points: &[(i32, i32, i32)], merges: &[Merge]) -> String {
+    let points_json: Vec<String> = points.iter().map(|p| format!("[{}, {}, {}]", p.0, p.1, p.2)).collect();
+    let merges_json: Vec<String> = merges
+        .iter()
+        .enumerate()
+        .map(|(k, merge)| {
+            format!(
+                "    {{\"id\": {}, \"left\": {}, \"right\": {}, \"size\": {}, \"points\": [{}, {}], \"exact_distance\": {}, \"distance\": {}}}",
+                points.len() + k, merge.left, merge.right, merge.size, merge.i, merge.j, merge.distance, merge.length
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"points\": [{}],\n  \"merges\": [\n{}\n  ]\n}}\n",
+        points_json.join(", "),
+        merges_json.join(",\n")
+    )
+}
+
+// Replay the first `merge_count` merges and list the circuits they leave, largest first
+fn cut_dendrogram(merges: &[Merge], n: usize, merge_count: usize) -> String {
+    let mut circuits = Circuits::new(n);
+    for merge in &merges[..merge_count] {
+        circuits.union(merge.i, merge.j);
+    }
+    let clusters = circuits.members();
+
+    let mut report = String::new();
+    for (idx, cluster) in clusters.iter().enumerate() {
+        let points: Vec<String> = cluster.iter().map(|point| point.to_string()).collect();
+        report.push_str(&format!("Circuit {} ({} points): {}\n", idx + 1, cluster.len(), points.join(" ")));
+    }
+    report
+}
+
+// The connections as an undirected Graphviz graph with each circuit drawn as a cluster,
+// points labelled with their index and position and edges with their distance
+fn graph_to_dot(metric: Metric, points: &[(i32, i32, i32)], edges: &[(i128, usize, usize)], clusters: &[Vec<usize>]) -> String {
+    let mut dot = String::from("graph circuits {\n");
+    for (idx, cluster) in clusters.iter().enumerate() {
+        dot.push_str(&format!("  subgraph cluster_{} {{\n", idx + 1));
+        dot.push_str(&format!("    label=\"Circuit {} ({} points)\";\n", idx + 1, cluster.len()));
+        for &point in cluster {
+            let p = points[point];
+            dot.push_str(&format!("    {} [label=\"{}\\n{},{},{}\"];\n", point, point, p.0, p.1, p.2));
+        }
+        dot.push_str("  }\n");
+    }
+    for &(distance, i, j) in edges {
+        dot.push_str(&format!("  {} -- {} [label=\"{:.2}\"];\n", i, j, metric.length(distance)));
+    }
+    dot.push_str("}\n");
+    dot
+}
 
-    if let Some((i, j)) = last_merge {
-        let last_point = list_of_points[i];
-        let second_last_point = list_of_points[j];
-        (last_point.0 * second_last_point.0).to_string()
-    } else {
-        "0".to_string()
+// The points with their position and circuit id (numbered from 1, largest circuit first) and
+// the connections with their exact and coordinate-unit distances
+fn graph_to_json(metric: Metric, points: &[(i32, i32, i32)], edges: &[(i128, usize, usize)], clusters: &[Vec<usize>]) -> String {
+    let mut circuit_of = vec![0; points.len()];
+    for (idx, cluster) in clusters.iter().enumerate() {
+        for &point in cluster {
+            circuit_of[point] = idx + 1;
+        }
+    }
+    let points_json: Vec<String> = points
+        .iter()
+        .enumerate()
+        .map(|(idx, p)| format!("    {{\"id\": {}, \"position\": [{}, {}, {}], \"circuit\": {}}}", idx, p.0, p.1, p.2, circuit_of[idx]))
+        .collect();
+    let edges_json: Vec<String> = edges
+        .iter()
+        .map(|&(distance, i, j)| {
+            format!("    {{\"from\": {}, \"to\": {}, \"exact_distance\": {}, \"distance\": {}}}", i, j, distance, metric.length(distance))
+        })
+        .collect();
+    format!(
+        "{{\n  \"circuits\": {},\n  \"points\": [\n{}\n  ],\n  \"edges\": [\n{}\n  ]\n}}\n",
+        clusters.len(),
+        points_json.join(",\n"),
+        edges_json.join(",\n")
+    )
+}
+
+// Write whichever of the DOT and JSON exports were asked for
+fn export_graph(args: &Args, metric: Metric, points: &[(i32, i32, i32)], edges: &[(i128, usize, usize)], clusters: &[Vec<usize>]) -> io::Result<()> {
+    if let Some(path) = &args.dot {
+        std::fs::write(path, graph_to_dot(metric, points, edges, clusters))?;
+    }
+    if let Some(path) = &args.graph_json {
+        std::fs::write(path, graph_to_json(metric, points, edges, clusters))?;
     }
+    Ok(())
+}
+
+// Connect all points using closest-first spanning (Kruskal) and
+// return the product of the X coordinates of the endpoints of the final merge.
+// The merge history can also be saved as a dendrogram or cut into circuits.
+fn solutioner_for_part_2(input: &str, args: &Args, metric: Metric) -> io::Result<String> {
+    let list_of_points = parse_list_of_points(input);
+    let n = list_of_points.len();
+    let merges = single_linkage(&list_of_points, metric);
+
+    if let Some(path) = &args.newick {
+        std::fs::write(path, dendrogram_to_newick(&merges, n))?;
+    }
+    if let Some(path) = &args.dendrogram_json {
+        std::fs::write(path, dendrogram_to_json(&list_of_points, &merges))?;
+    }
+    if let Some(threshold) = args.cut_distance {
+        let merge_count = merges.partition_point(|merge| merge.length <= threshold);
+        print!("{}", cut_dendrogram(&merges, n, merge_count));
+    }
+    if let Some(clusters) = args.cut_clusters {
+        let merge_count = n.saturating_sub(clusters.max(1)).min(merges.len());
+        print!("{}", cut_dendrogram(&merges, n, merge_count));
+    }
+
+    if args.dot.is_some() || args.graph_json.is_some() {
+        let edges: Vec<_> = merges.iter().map(|merge| (merge.distance, merge.i, merge.j)).collect();
+        let mut circuits = Circuits::new(n);
+        for &(_, i, j) in &edges {
+            circuits.union(i, j);
+        }
+        export_graph(args, metric, &list_of_points, &edges, &circuits.members())?;
+    }
+
+    Ok(match merges.last() {
+        Some(last_merge) => {
+            let last_point = list_of_points[last_merge.i];
+            let second_last_point = list_of_points[last_merge.j];
+            (last_point.0 as i64 * second_last_point.0 as i64).to_string()
+        }
+        None => "0".to_string(),
+    })
 }
 
 /// Main function to read input file, process it, and write to output file
@@ -213,9 +783,11 @@ fn main() -> io::Result<()> {
     let mut contents = String::new();
     input_file.read_to_string(&mut contents)?;
 
+    let metric = Metric::from_args(args.metric, &args.weights).map_err(io::Error::other)?;
     let processed = match args.part {
-        1 => solutioner_for_part_1(&contents),
-        2 => solutioner_for_part_2(&contents),
+        1 if args.sweep => solutioner_for_sweep(&contents, &args, metric),
+        1 => solutioner_for_part_1(&contents, &args, metric)?,
+        2 => solutioner_for_part_2(&contents, &args, metric)?,
         _ => "Invalid part specified".to_string(),
     };
 
@@ -226,3 +798,106 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: [Metric; 5] = [
+        Metric::Euclidean,
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::Weighted([1, 4, 9]),
+        Metric::Weighted([3, 0, 1]),
+    ];
+
+    // Deterministic pseudo-random points from a linear congruential generator
+    fn random_points(seed: u64, count: usize, range: i32) -> Vec<(i32, i32, i32)> {
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) % range as u64) as i32 - range / 2
+        };
+        (0..count).map(|_| (next(), next(), next())).collect()
+    }
+
+    // Every pair sorted by (distance, i, j), the order the kd-tree walk promises
+    fn brute_force_pairs(points: &[(i32, i32, i32)], metric: Metric) -> Vec<(i128, usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..points.len() {
+            for j in (i + 1)..points.len() {
+                pairs.push((metric.distance(points[i], points[j]), i, j));
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn metric_distances() {
+        let (p, q) = ((1, -2, 3), (4, 2, -9));
+        assert_eq!(Metric::Euclidean.distance(p, q), 9 + 16 + 144);
+        assert_eq!(Metric::Manhattan.distance(p, q), 3 + 4 + 12);
+        assert_eq!(Metric::Chebyshev.distance(p, q), 12);
+        assert_eq!(Metric::Weighted([1, 4, 9]).distance(p, q), 9 + 64 + 1296);
+    }
+
+    #[test]
+    fn closest_pairs_match_brute_force() {
+        // A small range gives lots of tied distances and repeated points
+        for (seed, range) in [(1, 10), (2, 1000), (3, 2_000_000)] {
+            let points = random_points(seed, 150, range);
+            for metric in METRICS {
+                let pairs: Vec<_> = ClosestPairs::new(&points, metric).collect();
+                assert_eq!(pairs, brute_force_pairs(&points, metric), "{:?} with range {}", metric, range);
+            }
+        }
+    }
+
+    #[test]
+    fn closest_pairs_are_exact_for_large_coordinates() {
+        // Squared distances here are far past 2^53 where the kd-tree's f64 distances round,
+        // so the tree sees the far cluster as all tied. Listing it both ways round makes sure
+        // the tree's order within the tie doesn't happen to line up with the exact one.
+        let cluster: Vec<_> = (0..22).map(|y| (1_000_000_000, y, 0)).collect();
+        for reversed in [false, true] {
+            let mut points = vec![(-2_000_000_000, 0, 0)];
+            if reversed {
+                points.extend(cluster.iter().rev());
+            } else {
+                points.extend(&cluster);
+            }
+            for metric in METRICS {
+                let pairs: Vec<_> = ClosestPairs::new(&points, metric).collect();
+                assert_eq!(pairs, brute_force_pairs(&points, metric), "{:?} reversed {}", metric, reversed);
+            }
+        }
+
+        for seed in [6, 7] {
+            let points = random_points(seed, 150, i32::MAX);
+            for metric in METRICS {
+                let pairs: Vec<_> = ClosestPairs::new(&points, metric).collect();
+                assert_eq!(pairs, brute_force_pairs(&points, metric), "{:?} with seed {}", metric, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn single_linkage_matches_brute_force() {
+        for (seed, range) in [(4, 20), (5, 100_000)] {
+            let points = random_points(seed, 300, range);
+            for metric in METRICS {
+                let mut circuits = Circuits::new(points.len());
+                let expected: Vec<_> = brute_force_pairs(&points, metric)
+                    .into_iter()
+                    .filter(|&(_, i, j)| circuits.union(i, j))
+                    .collect();
+                let merges: Vec<_> = single_linkage(&points, metric)
+                    .into_iter()
+                    .map(|merge| (merge.distance, merge.i, merge.j))
+                    .collect();
+                assert_eq!(merges, expected, "{:?} with range {}", metric, range);
+            }
+        }
+    }
+}